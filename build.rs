@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Record the resolved versions of the solver dependencies, reported by `version_info()`.
+
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (name, var) in [
+        ("elm-solve-deps", "ELM_SOLVE_DEPS_VERSION"),
+        ("pubgrub", "PUBGRUB_VERSION"),
+    ] {
+        let version = locked_version(&lock, name).unwrap_or("unknown");
+        println!("cargo:rustc-env={}={}", var, version);
+    }
+}
+
+/// Version of a package in the content of a Cargo.lock file.
+fn locked_version<'a>(lock: &'a str, name: &str) -> Option<&'a str> {
+    let name_line = format!("name = \"{}\"", name);
+    let mut lines = lock.lines();
+    lines.find(|line| *line == name_line)?;
+    let version = lines.next()?.strip_prefix("version = \"")?;
+    version.strip_suffix('"')
+}
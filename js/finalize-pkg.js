@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// Copy the additional JS entry points into the wasm-pack generated `pkg/`
+// folder and register them in its package.json.
+//
+// Usage: node js/finalize-pkg.js pkg
+
+const fs = require('fs');
+const path = require('path');
+
+// Extra files shipped with the package, with their subpath export.
+const extraEntries = {
+  './promise': 'promise.js',
+  './worker': 'worker.js',
+  './worker-client': 'worker-client.js',
+};
+
+const pkgDir = process.argv[2] || 'pkg';
+const packageJsonPath = path.join(pkgDir, 'package.json');
+const packageJson = JSON.parse(fs.readFileSync(packageJsonPath, 'utf8'));
+
+const exportsMap = { '.': './' + packageJson.main };
+for (const [subpath, file] of Object.entries(extraEntries)) {
+  fs.copyFileSync(path.join(__dirname, file), path.join(pkgDir, file));
+  if (!packageJson.files.includes(file)) {
+    packageJson.files.push(file);
+  }
+  exportsMap[subpath] = './' + file;
+}
+exportsMap['./package.json'] = './package.json';
+packageJson.exports = exportsMap;
+
+fs.writeFileSync(packageJsonPath, JSON.stringify(packageJson, null, 2) + '\n');
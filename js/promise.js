@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// Promise-based entry point, available as `elm-solve-deps-wasm/promise`.
+//
+// It has the same functions than the root entry point, all returning promises,
+// and the `fetchElmJson` and `listAvailableVersions` callbacks may be async.
+// Callbacks given in the options must stay synchronous.
+//
+// The wasm solver itself is synchronous, so it cannot wait on async callbacks.
+// Instead, each attempt only reads from in-memory caches.
+// When the solver needs something that is not cached yet, the attempt is aborted,
+// the missing data is awaited with the async callbacks, and the call restarts.
+// Each elm.json and each versions list is thus requested at most once per call.
+// To keep restarts rare, every fetched elm.json also prefetches the newest versions
+// of its dependencies, which are the ones the solver tries first.
+// The console output of aborted attempts is dropped, only the last attempt is logged,
+// and the callbacks of the options are called once per distinct arguments.
+//
+//   const wasm = require('elm-solve-deps-wasm/promise');
+//   await wasm.init();
+//   const solution = await wasm.solve_deps(
+//     elmJson, useTest, additionalConstraints,
+//     async (pkg, version) => ..., // resolves to the elm.json string
+//     async (pkg) => ...           // resolves to the array of versions
+//   );
+
+const wasm = require('./elm_solve_deps_wasm.js');
+
+// Thrown inside the sync callbacks to abort an attempt missing some data.
+const MISSING = 'elm-solve-deps-wasm: missing data, retrying';
+
+// Exports whose function arguments are not data sources, called without restarts.
+const DIRECT = new Set(['init', 'set_span_subscriber']);
+
+// Callbacks of the options, or methods of a provider, memoized across attempts.
+const MEMOIZED = ['approve', 'chooseVersion', 'hasLocally', 'packagePriority', 'fetchEndpoint', 'score'];
+
+// Console methods used by the logger, buffered during an attempt.
+const CONSOLE_METHODS = ['log', 'debug', 'info', 'warn', 'error', 'group', 'groupEnd'];
+
+// Async data sources shared by the attempts of one call.
+class Loader {
+  constructor(fetchElmJson, listAvailableVersions, thisArg, options) {
+    this.fetchElmJson = fetchElmJson;
+    this.listAvailableVersions = listAvailableVersions;
+    this.thisArg = thisArg;
+    // Callbacks get the `context` of the options as last argument.
+    this.extraArgs = options && options.context !== undefined ? [options.context] : [];
+    this.elmJsons = new Map();
+    this.versionsLists = new Map();
+    this.tasks = new Set();
+    this.missed = false;
+  }
+
+  // Start loading a value, settling the returned entry without ever rejecting.
+  start(load) {
+    const entry = { done: false };
+    entry.promise = Promise.resolve()
+      .then(load)
+      .then(
+        (value) => Object.assign(entry, { done: true, value }),
+        (error) => Object.assign(entry, { done: true, failed: true, error })
+      );
+    this.track(entry.promise);
+    return entry;
+  }
+
+  track(promise) {
+    this.tasks.add(promise);
+    promise.then(() => this.tasks.delete(promise));
+  }
+
+  // Wait until everything started, including prefetches, is loaded.
+  async settle() {
+    while (this.tasks.size > 0) {
+      await Promise.all([...this.tasks]);
+    }
+  }
+
+  elmJson(pkg, version) {
+    const key = pkg + '@' + version;
+    if (!this.elmJsons.has(key)) {
+      const entry = this.start(() =>
+        this.fetchElmJson.call(this.thisArg, pkg, version, ...this.extraArgs)
+      );
+      this.elmJsons.set(key, entry);
+      this.track(entry.promise.then(() => entry.failed || this.prefetch(entry.value)));
+    }
+    return this.elmJsons.get(key);
+  }
+
+  versions(pkg) {
+    if (!this.versionsLists.has(pkg)) {
+      const entry = this.start(() =>
+        this.listAvailableVersions.call(this.thisArg, pkg, ...this.extraArgs)
+      );
+      this.versionsLists.set(pkg, entry);
+    }
+    return this.versionsLists.get(pkg);
+  }
+
+  // Synchronous read for the solver, aborting the attempt if not loaded yet.
+  read(entry) {
+    if (!entry.done) {
+      this.missed = true;
+      throw MISSING;
+    }
+    if (entry.failed) {
+      throw entry.error;
+    }
+    return entry.value;
+  }
+
+  // Prefetch the dependencies of an elm.json, which may be wrapped in `{ elmJson, source }`.
+  // Failures are ignored, since the solver may never need those.
+  prefetch(elmJson) {
+    let dependencies;
+    try {
+      const json = typeof elmJson === 'string' ? elmJson : elmJson.elmJson;
+      dependencies = JSON.parse(json).dependencies || {};
+    } catch (_) {
+      return;
+    }
+    for (const [pkg, constraint] of Object.entries(dependencies)) {
+      if (typeof constraint === 'string') {
+        this.prefetchNewest(pkg, constraint);
+      } else {
+        // The direct and indirect dependencies of an application, with exact versions.
+        for (const [appPkg, version] of Object.entries(constraint || {})) {
+          this.versions(appPkg);
+          this.elmJson(appPkg, version);
+        }
+      }
+    }
+  }
+
+  prefetchNewest(pkg, constraint) {
+    const entry = this.versions(pkg);
+    this.track(
+      entry.promise.then(() => {
+        if (entry.failed) {
+          return;
+        }
+        try {
+          const allowed = wasm.filter_versions(constraint, entry.value);
+          const newest = wasm.sort_versions(allowed, 'highestFirst')[0];
+          if (newest !== undefined) {
+            this.elmJson(pkg, newest);
+          }
+        } catch (_) {}
+      })
+    );
+  }
+}
+
+// Memoize a callback by its arguments, except the trailing `context` of the options.
+function memoize(callback, extraArgs) {
+  const results = new Map();
+  return function (...args) {
+    const key = JSON.stringify(args.slice(0, args.length - extraArgs.length));
+    if (!results.has(key)) {
+      results.set(key, callback.apply(this, args));
+    }
+    return results.get(key);
+  };
+}
+
+// Set memoized versions of the callbacks of an object, options or provider, on a copy of it.
+function withMemoizedCallbacks(object, copy, extraArgs) {
+  for (const name of MEMOIZED) {
+    if (typeof object[name] === 'function') {
+      copy[name] = memoize(object[name], extraArgs);
+    }
+  }
+  return copy;
+}
+
+// Run an attempt with its console output buffered,
+// replayed afterwards only if `keep` says the attempt counts.
+function buffered(attempt, keep) {
+  const calls = [];
+  const original = {};
+  const timers = new Map();
+  for (const method of CONSOLE_METHODS) {
+    original[method] = console[method];
+    console[method] = (...args) => calls.push([method, args]);
+  }
+  // Timings are measured now, but only written when replayed.
+  original.time = console.time;
+  original.timeEnd = console.timeEnd;
+  console.time = (label = 'default') => timers.set(label, Date.now());
+  console.timeEnd = (label = 'default') => {
+    const duration = Date.now() - timers.get(label);
+    calls.push(['info', [label + ': ' + duration + 'ms']]);
+  };
+  const replay = () => {
+    Object.assign(console, original);
+    if (keep()) {
+      for (const [method, args] of calls) {
+        console[method](...args);
+      }
+    }
+  };
+  try {
+    const result = attempt();
+    replay();
+    return result;
+  } catch (error) {
+    replay();
+    throw error;
+  }
+}
+
+// Call a wasm function with async data sources, restarting it until nothing is missing.
+async function withLoader(wasmFunction, args) {
+  const fetchIndex = args.findIndex((arg) => typeof arg === 'function');
+  const providerIndex = args.findIndex(
+    (arg) => arg !== null && typeof arg === 'object' && typeof arg.fetchElmJson === 'function'
+  );
+  let loader;
+  let optionsIndex;
+  const attemptArgs = args.slice();
+  if (fetchIndex >= 0) {
+    optionsIndex = fetchIndex + 2;
+    const options = args[optionsIndex];
+    loader = new Loader(args[fetchIndex], args[fetchIndex + 1], options && options.thisArg, options);
+    if (options) {
+      const copy = Object.assign({}, options);
+      attemptArgs[optionsIndex] = withMemoizedCallbacks(options, copy, loader.extraArgs);
+    }
+    attemptArgs[fetchIndex] = (pkg, version) => loader.read(loader.elmJson(pkg, version));
+    attemptArgs[fetchIndex + 1] = (pkg) => loader.read(loader.versions(pkg));
+  } else if (providerIndex >= 0) {
+    optionsIndex = providerIndex + 1;
+    const provider = args[providerIndex];
+    loader = new Loader(
+      provider.fetchElmJson,
+      provider.listAvailableVersions,
+      provider,
+      args[optionsIndex]
+    );
+    const options = args[optionsIndex];
+    if (options) {
+      const copy = Object.assign({}, options);
+      attemptArgs[optionsIndex] = withMemoizedCallbacks(options, copy, loader.extraArgs);
+    }
+    // Other methods of the provider are inherited, still reading its fields.
+    const inheriting = withMemoizedCallbacks(provider, Object.create(provider), loader.extraArgs);
+    attemptArgs[providerIndex] = Object.assign(inheriting, {
+      fetchElmJson: (pkg, version) => loader.read(loader.elmJson(pkg, version)),
+      listAvailableVersions: (pkg) => loader.read(loader.versions(pkg)),
+    });
+  } else {
+    return wasmFunction(...args);
+  }
+  if (typeof args[0] === 'string') {
+    loader.prefetch(args[0]);
+  }
+  for (;;) {
+    loader.missed = false;
+    try {
+      // Aborted attempts fail with an error, which should not be logged.
+      const result = buffered(() => wasmFunction(...attemptArgs), () => !loader.missed);
+      if (!loader.missed) {
+        return result;
+      }
+    } catch (error) {
+      if (!loader.missed) {
+        throw error;
+      }
+    }
+    // Some data was missing, load it and try again.
+    await loader.settle();
+  }
+}
+
+for (const [name, value] of Object.entries(wasm)) {
+  if (typeof value !== 'function' || name.startsWith('__')) {
+    continue;
+  }
+  module.exports[name] = DIRECT.has(name)
+    ? async (...args) => value(...args)
+    : async (...args) => withLoader(value, args);
+}
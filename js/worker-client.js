@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// Main-thread half of the worker wrapper.
+// Wrap a worker running `expose()` (see worker.js) to get a Promise-based
+// `solve_deps` that never blocks the main thread.
+//
+//   const { Worker } = require('worker_threads');
+//   const { wrap } = require('elm-solve-deps-wasm/worker-client');
+//   const solver = wrap(new Worker('./my-solver-worker.js'));
+//   const solution = await solver.solve_deps(elmJson, false, {});
+//
+// Options are cloned to the worker with `postMessage`, so they cannot contain
+// functions, like `packagePriority`, nor an `AbortSignal`:
+// the returned promise is then rejected with a `DataCloneError`.
+
+// Wrap a node `Worker` or a browser `Worker`.
+function wrap(worker /*: any */) /*: {
+  solve_deps: (string, boolean, { [string]: string }, ?{}) => Promise<string>,
+  terminate: () => void,
+} */ {
+  let nextId = 0;
+  const pending /*: Map<number, {| resolve: (string) => void, reject: (Error) => void |}> */ =
+    new Map();
+
+  function onMessage({ id, solution, error }) {
+    const request = pending.get(id);
+    if (request === undefined) {
+      return;
+    }
+    pending.delete(id);
+    if (error !== undefined) {
+      request.reject(deserializeError(error));
+    } else {
+      request.resolve(solution);
+    }
+  }
+
+  if (typeof worker.on === 'function') {
+    worker.on('message', onMessage);
+  } else {
+    worker.addEventListener('message', (event) => onMessage(event.data));
+  }
+
+  // Rebuild an error serialized by the worker.
+  function deserializeError({ name, message, fields }) /*: Error */ {
+    const error = Object.assign(new Error(message), fields);
+    error.name = name;
+    return error;
+  }
+
+  function solve_deps(
+    elmJson /*: string */,
+    useTest /*: boolean */,
+    additionalConstraints /*: { [string]: string } */,
+    options /*: ?{} */
+  ) /*: Promise<string> */ {
+    const id = nextId++;
+    return new Promise(function (resolve, reject) {
+      pending.set(id, { resolve, reject });
+      worker.postMessage({
+        id,
+        elmJson,
+        useTest,
+        additionalConstraints,
+        options,
+      });
+    });
+  }
+
+  function terminate() {
+    for (const request of pending.values()) {
+      request.reject(new Error('The solver worker was terminated.'));
+    }
+    pending.clear();
+    worker.terminate();
+  }
+
+  return { solve_deps, terminate };
+}
+
+module.exports = {
+  wrap,
+};
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// Worker-side half of the worker wrapper.
+// Call `expose(provider)` from your worker script with an object providing
+// the two dependency callbacks. Every `solve_deps` request received over
+// `postMessage` is then solved inside the worker, and only plain data
+// (strings and objects of strings) ever crosses the worker boundary.
+// Errors are sent as plain objects, and rebuilt as `Error` by the client
+// with the same message and fields.
+//
+//   // my-solver-worker.js
+//   const { expose } = require('elm-solve-deps-wasm/worker');
+//   expose(require('./my-dependency-provider.js'));
+
+const wasm = require('./elm_solve_deps_wasm.js');
+
+// Start answering solve requests sent to this worker.
+// The provider must have `fetchElmJson(pkg, version)`
+// and `listAvailableVersions(pkg)` functions.
+function expose(provider /*: {
+  fetchElmJson: (string, string) => string,
+  listAvailableVersions: (string) => Array<string>,
+} */) /*: void */ {
+  wasm.init();
+  const port = workerPort();
+  port.onMessage(function ({
+    id,
+    elmJson,
+    useTest,
+    additionalConstraints,
+    options,
+  }) {
+    try {
+      const solution = wasm.solve_deps(
+        elmJson,
+        useTest,
+        additionalConstraints,
+        provider.fetchElmJson,
+        provider.listAvailableVersions,
+        options
+      );
+      port.postMessage({ id, solution });
+    } catch (error) {
+      port.postMessage({ id, error: serializeError(error) });
+    }
+  });
+}
+
+// Plain data version of an error, keeping its message and its fields,
+// like the `errorTree` of the errors of the solver.
+// Fields that cannot be cloned are dropped.
+function serializeError(error /*: any */) /*: {
+  name: string,
+  message: string,
+  fields: { [string]: any },
+} */ {
+  if (error === null || typeof error !== 'object') {
+    return { name: 'Error', message: String(error), fields: {} };
+  }
+  const fields = {};
+  for (const [key, value] of Object.entries(error)) {
+    try {
+      fields[key] = JSON.parse(JSON.stringify(value));
+    } catch (_) {
+      // Not serializable, skip it.
+    }
+  }
+  return {
+    name: String(error.name || 'Error'),
+    message: String(error.message),
+    fields,
+  };
+}
+
+// Abstract over node worker threads and browser web workers.
+function workerPort() /*: {
+  onMessage: ((any) => void) => void,
+  postMessage: (any) => void,
+} */ {
+  let parentPort = null;
+  try {
+    // $FlowFixMe[cannot-resolve-module]
+    parentPort = require('worker_threads').parentPort;
+  } catch (_) {
+    // Not running in node, we must be in a web worker.
+  }
+  if (parentPort) {
+    return {
+      onMessage: (handler) => parentPort.on('message', handler),
+      postMessage: (msg) => parentPort.postMessage(msg),
+    };
+  }
+  return {
+    onMessage: (handler) =>
+      self.addEventListener('message', (event) => handler(event.data)),
+    postMessage: (msg) => self.postMessage(msg),
+  };
+}
+
+module.exports = {
+  expose,
+};
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Security advisories on package versions, avoided by the solver or reported with the solution.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::project_config::{AppDependencies, Pkg};
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::constraints;
+use crate::options;
+
+/// Advisory on the versions of a package affected by a vulnerability.
+#[derive(Clone)]
+pub struct Advisory {
+    pub pkg: Pkg,
+    pub affected_range: Range<SemVer>,
+    severity: String,
+    id: String,
+}
+
+/// Vulnerable package version of a solution.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vulnerability {
+    package: Pkg,
+    version: SemVer,
+    id: String,
+    severity: String,
+    affected_range: String,
+}
+
+/// Parse the advisories of the options, whose affected ranges have the syntax
+/// of additional constraints, like `1.0.0 <= v < 1.2.3 && != 1.1.0`.
+pub fn parse(advisories: &[options::Advisory]) -> anyhow::Result<Vec<Advisory>> {
+    (advisories.iter())
+        .map(|a| {
+            let affected_range = constraints::parse(&a.affected_range)
+                .with_context(|| format!("Invalid affected range of advisory {}", a.id))?;
+            Ok(Advisory {
+                pkg: Pkg::from_str(&a.pkg)?,
+                affected_range: affected_range.0,
+                severity: a.severity.clone(),
+                id: a.id.clone(),
+            })
+        })
+        .collect()
+}
+
+/// List the package versions of a solution affected by an advisory.
+pub fn vulnerabilities(solution: &AppDependencies, advisories: &[Advisory]) -> Vec<Vulnerability> {
+    let mut vulnerabilities = Vec::new();
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        for advisory in advisories {
+            if &advisory.pkg == pkg && advisory.affected_range.contains(version) {
+                vulnerabilities.push(Vulnerability {
+                    package: pkg.clone(),
+                    version: *version,
+                    id: advisory.id.clone(),
+                    severity: advisory.severity.clone(),
+                    affected_range: advisory.affected_range.to_string(),
+                });
+            }
+        }
+    }
+    vulnerabilities
+}
@@ -0,0 +1,392 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Analyses of the dependencies of projects, like upgrades, audits or conflicts.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+
+use super::{parse_js_constraints, parse_project_elm_json, parse_solution, with_solve_context};
+use crate::{correction, lint, metadata, pipeline, reach, solver, suggest, upgrades, utils};
+
+/// Check that a package `elm.json` is ready to be published.
+///
+/// Constraints must have the form `low <= v < high`, with an upper bound that is
+/// a major version, like `1.0.0 <= v < 2.0.0`, and the elm version must allow
+/// the `elmVersion` option, elm 0.19.1 by default.
+/// When constraints are well shaped, dependencies, and then test dependencies,
+/// must also have a solution.
+/// Return the list of findings, each with the elm.json `field`,
+/// the `package` if relevant, and a `message`. No finding means the package is publishable.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn check_publishable(
+    package_elm_json_str: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let options = &context.options;
+            let elm_json: serde_json::Value = serde_json::from_str(package_elm_json_str)
+                .context("Failed to decode the elm.json")
+                .map_err(utils::report_error)?;
+            let mut findings = lint::publishable(&elm_json, options.target_elm_version());
+            if findings.is_empty() {
+                let project_elm_json = parse_project_elm_json(package_elm_json_str)?;
+                let provider = context.project_provider(&project_elm_json);
+                for (field, use_test) in [("dependencies", false), ("test-dependencies", true)] {
+                    let result = pipeline::tracked_solve(&provider, || {
+                        solver::solve_deps_js(&project_elm_json, use_test, &[], &provider, false)
+                    });
+                    match result {
+                        Ok(_) => {}
+                        Err(err @ PubGrubError::NoSolution(_)) => {
+                            let message = format!("{:#}", pipeline::handle_pubgrub_error(err));
+                            findings.push(lint::Finding::new(field, None, message));
+                            break;
+                        }
+                        Err(err) => {
+                            return Err(utils::report_error(pipeline::handle_pubgrub_error(err)))
+                        }
+                    }
+                }
+            }
+            Ok(serde_wasm_bindgen::to_value(&findings)?)
+        },
+    )
+}
+
+/// Suggest the widest constraints of the dependencies of a package.
+///
+/// For each dependency, the constraint is extended with consecutive versions below it,
+/// and with complete major versions above it, as long as the dependencies
+/// still have a solution with each of those versions and the other constraints unchanged.
+/// Return the list of dependencies whose constraint can be loosened,
+/// each with the `package`, and its `current` and `widest` constraints,
+/// written like in `elm.json`.
+/// This performs one solve per version checked, so it can take a while.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn suggest_widest_constraints(
+    package_elm_json_str: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(package_elm_json_str)?;
+            if let ProjectConfig::Application(_) = project_elm_json {
+                return Err(utils::report_error(anyhow::anyhow!(
+                    "The elm.json is not the one of a package"
+                )));
+            }
+            let provider = context.project_provider(&project_elm_json);
+            let result = pipeline::tracked_solve(&provider, || {
+                suggest::widest_constraints(&project_elm_json, &provider)
+            });
+            match result {
+                Ok(suggestions) => Ok(serde_wasm_bindgen::to_value(&suggestions)?),
+                Err(err) => Err(utils::report_error(pipeline::handle_pubgrub_error(err))),
+            }
+        },
+    )
+}
+
+/// Report the newer versions available for the packages of the solution of a project.
+///
+/// The project dependencies (without test dependencies) are solved first.
+/// Then for each package of the solution, the report contains the `package`,
+/// its `current` version, if it is a `direct` dependency, and the latest newer
+/// `patch`, `minor` and `major` versions if any.
+/// Each newer version comes with what prevents adopting it:
+/// whether the dependencies are `solvable` with it,
+/// whether the project `ownConstraint` excludes it (only for packages),
+/// and the list of packages whose dependencies exclude it (`blockedBy`).
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn upgrade_report(
+    project_elm_json_str: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            let provider = context.project_provider(&project_elm_json);
+            let result = pipeline::tracked_solve(&provider, || {
+                let solution =
+                    solver::solve_deps_js(&project_elm_json, false, &[], &provider, false)?;
+                upgrades::upgrades(&project_elm_json, &solution, &provider)
+            });
+            match result {
+                Ok(report) => Ok(serde_wasm_bindgen::to_value(&report)?),
+                Err(err) => Err(utils::report_error(pipeline::handle_pubgrub_error(err))),
+            }
+        },
+    )
+}
+
+/// Find which constraints of the project to remove to make the resolution possible.
+///
+/// When there is no solution, the proof of pubgrub explains the conflict,
+/// but not which of the project constraints to change.
+/// This returns a minimal set of the project constraints whose removal makes the
+/// resolution possible, as an array of objects with the `package`, its `constraint`,
+/// and its `source`: `"dependencies"`, `"test-dependencies"` or `"additional"`.
+/// The set is minimal in that adding back any of its constraints makes the resolution
+/// impossible again, but there may be other such sets.
+/// It is empty if there is already a solution.
+/// This needs one solve per constraint of the project.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn minimal_correction_set(
+    project_elm_json_str: &str,
+    use_test: bool,
+    // additional_constraints_str: &HashMap<String, Constraint>,
+    additional_constraints_str: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            let additional_constraints = parse_js_constraints(additional_constraints_str)?;
+            let provider = context.project_provider(&project_elm_json);
+            let result = pipeline::tracked_solve(&provider, || {
+                correction::minimal_correction_set(
+                    &project_elm_json,
+                    use_test,
+                    &additional_constraints,
+                    &provider,
+                )
+            });
+            match result {
+                Ok(removals) => Ok(serde_wasm_bindgen::to_value(&removals)?),
+                Err(err) => Err(utils::report_error(pipeline::handle_pubgrub_error(err))),
+            }
+        },
+    )
+}
+
+/// List the packages of a solution incompatible with a version of the elm compiler.
+///
+/// The solution is the JSON string returned by `solve_deps`,
+/// and `elm_version` is the targeted compiler version, like `"0.19.1"`.
+/// Return an array with the `package`, its `version` and its `elmVersion` range,
+/// for every package of the solution whose `elm-version` range excludes the targeted version.
+/// This helps to plan a migration to another compiler release.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn incompatible_packages(
+    solution_str: &str,
+    elm_version: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let solution = parse_solution(solution_str)?;
+            let elm_version = SemVer::from_str(elm_version)
+                .context("Invalid elm version")
+                .map_err(utils::report_error)?;
+            let provider = context.provider();
+            let incompatible = metadata::incompatible_packages(&solution, elm_version, &provider)
+                .map_err(utils::report_error_message)?;
+            Ok(serde_wasm_bindgen::to_value(&incompatible)?)
+        },
+    )
+}
+
+/// Audit the dependencies of a project, as a gate for continuous integration.
+///
+/// The report combines several checks of the `elm.json`:
+///  - `layout`: packages listed in several sections (error).
+///  - `lockfile`: for applications, recorded versions that do not satisfy the constraints
+///    of the recorded packages, or missing packages (error).
+///  - `minimality`: for applications, indirect dependencies that no direct dependency needs (warning).
+///  - `outdated`: for applications, recorded packages with a newer version available,
+///    and for packages, constraints excluding the latest version of a dependency (info).
+///
+/// Return an object with `passed` set to `true` if there is no error,
+/// the number of `errors` and `warnings`, and the list of `items` found,
+/// each with its `severity` (`"error"`, `"warning"` or `"info"`), the `check` that found it,
+/// the `package` concerned, and a `message`.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn audit(
+    project_elm_json_str: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            let provider = context.project_provider(&project_elm_json);
+            let report = crate::audit::audit(&project_elm_json, &provider)
+                .map_err(utils::report_error_message)?;
+            Ok(serde_wasm_bindgen::to_value(&report)?)
+        },
+    )
+}
+
+/// Newly published versions, as listed by the package registry.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum NewVersions {
+    /// Array of `"author/package@version"`, like the `/all-packages/since/N` registry endpoint.
+    Since(Vec<String>),
+    /// Object mapping packages to their new versions.
+    Map(HashMap<String, Vec<String>>),
+}
+
+/// Report the newly published versions that could change the solution of a project.
+///
+/// `new_versions` is either an array of `"author/package@version"` strings,
+/// as returned by the `/all-packages/since/N` endpoint of the package registry,
+/// or an object mapping packages to arrays of versions, like the difference
+/// between two snapshots of `/all-packages`.
+/// A new version is relevant if it is allowed by the constraints on its package
+/// reachable from the project, exploring every version allowed by those constraints.
+/// Return the list of relevant releases, each with the `package`, the `version`,
+/// and the reachable `constraint` allowing it.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn registry_diff(
+    project_elm_json_str: &str,
+    use_test: bool,
+    new_versions: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            let new_versions = match serde_wasm_bindgen::from_value(new_versions)? {
+                NewVersions::Since(releases) => (releases.iter())
+                    .map(|release| {
+                        let (pkg, version) = release
+                            .split_once('@')
+                            .with_context(|| format!("Invalid release {}", release))?;
+                        Ok((Pkg::from_str(pkg)?, SemVer::from_str(version)?))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>(),
+                NewVersions::Map(versions) => (versions.iter())
+                    .flat_map(|(pkg, versions)| versions.iter().map(move |v| (pkg, v)))
+                    .map(|(pkg, version)| Ok((Pkg::from_str(pkg)?, SemVer::from_str(version)?)))
+                    .collect::<anyhow::Result<Vec<_>>>(),
+            }
+            .context("Failed to decode the new versions")
+            .map_err(utils::report_error)?;
+            let provider = context.project_provider(&project_elm_json);
+            let reach = pipeline::tracked_solve(&provider, || {
+                reach::reachable_constraints(&project_elm_json, use_test, &provider)
+            })
+            .map_err(utils::report_error_message)?;
+            let releases = upgrades::relevant_releases(&reach, &new_versions);
+            Ok(serde_wasm_bindgen::to_value(&releases)?)
+        },
+    )
+}
+
+/// List the constraints imposed on a package by the project and the packages of its solution.
+///
+/// The solution is the JSON string returned by `solve_deps`.
+/// Return an object with the `package`, the list of `constraints`, each with
+/// its `dependent` (`root` for the project itself), the dependent `version`,
+/// and the `constraint` it imposes, and the `intersection` of all those constraints,
+/// with the syntax of additional constraints.
+/// The project constraints include its test dependencies.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn constraints_on(
+    project_elm_json_str: &str,
+    solution_str: &str,
+    pkg: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            let solution = parse_solution(solution_str)?;
+            let pkg = Pkg::from_str(pkg)
+                .context("Failed to decode the package")
+                .map_err(utils::report_error)?;
+            let provider = context.project_provider(&project_elm_json);
+            let constraints =
+                metadata::constraints_on(&project_elm_json, &solution, &pkg, &provider)
+                    .map_err(utils::report_error_message)?;
+            Ok(serde_wasm_bindgen::to_value(&constraints)?)
+        },
+    )
+}
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Timing spans, benchmarks and statistics of the solver.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{info, spans, stats, utils};
+
+/// Set a JS function receiving timing spans of the solver, to analyze where time goes.
+///
+/// The subscriber is called with an event object when entering and exiting each span,
+/// with its `kind` (`"enter"` or `"exit"`), its `name`, its `fields` (an object of strings),
+/// the `time` of the event in milliseconds, and the `duration` of the span on exit.
+/// Spans cover the phases of `solve_deps` and `solve_test_deps`
+/// (`"parsing"`, `"fetching"`, `"resolution"`, `"reporting"`),
+/// each call to `fetch_elm_json` (with its `pkg` and `version` fields)
+/// and `list_available_versions` (with its `pkg` field),
+/// and each `"decision"` of the solver (with the `pkg` and `version` chosen on exit).
+/// Passing `null` or `undefined` removes the subscriber.
+#[wasm_bindgen]
+pub fn set_span_subscriber(
+    // subscriber(event: SpanEvent) -> void, or null
+    subscriber: Option<js_sys::Function>,
+) {
+    spans::set_subscriber(subscriber);
+}
+
+/// Benchmark the solver on built-in synthetic registries.
+///
+/// The `scenario` is one of `"deep-chain"` (a long chain of dependencies),
+/// `"wide-fanout"` (hundreds of direct dependencies sharing a common one),
+/// `"backtracking"` (a chain that must be walked again for each version of a shared package),
+/// or `"all"` to run them all.
+/// Each scenario is solved `iterations` times (1 by default), without JS callbacks.
+/// Return an array with the results of each scenario: its `scenario` name,
+/// the number of `iterations`, the number of `packages` in the solution,
+/// and the `totalMs`, `meanMs`, `minMs` and `maxMs` times of the solves.
+#[wasm_bindgen]
+pub fn bench(scenario: &str, iterations: Option<u32>) -> Result<JsValue, JsValue> {
+    let iterations = iterations.unwrap_or(1);
+    let scenarios = if scenario == "all" {
+        crate::bench::SCENARIOS.to_vec()
+    } else {
+        vec![scenario]
+    };
+    let results = (scenarios.into_iter())
+        .map(|scenario| crate::bench::run(scenario, iterations))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(utils::report_error)?;
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Generate a random registry, to stress the solver and the JS callbacks without real data.
+///
+/// The optional `params` object accepts the `seed` of the random generator (0 by default),
+/// the number of `packages` (100), of `versionsPerPackage` (10),
+/// the maximum number of `dependenciesPerVersion` (3),
+/// the number of `directDependencies` of the generated project (5),
+/// and the `tightness` of constraints (0.2), the probability between 0 and 1
+/// that a constraint only allows one minor version instead of a whole major version.
+/// Return an object with the `elmJson` string of a package project depending on the registry,
+/// the `versions` of each package (newest first), and the `packages` object mapping
+/// each package and version to its elm.json string, which is all that is needed
+/// to implement `fetch_elm_json` and `list_available_versions`.
+/// The generated project may have no solution.
+#[wasm_bindgen]
+pub fn generate_registry(
+    // params: RegistryParams, can be omitted
+    params: JsValue,
+) -> Result<JsValue, JsValue> {
+    let params: crate::bench::RegistryParams = if params.is_undefined() || params.is_null() {
+        crate::bench::RegistryParams::default()
+    } else {
+        serde_wasm_bindgen::from_value(params)?
+    };
+    let registry = crate::bench::generate_registry(&params);
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(serde::Serialize::serialize(&registry, &serializer)?)
+}
+
+/// Report the versions of this module and of the libraries it is built upon.
+///
+/// The returned object contains the `version` of elm-solve-deps-wasm,
+/// the versions of the `elmSolveDeps` and `pubgrub` libraries,
+/// the cargo `features` enabled in this build,
+/// and its size `profile` (`"default"`, `"tiny"` or `"minimal"`).
+/// It is useful to include in bug reports.
+#[wasm_bindgen]
+pub fn version_info() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&info::version_info())?)
+}
+
+/// Report the memory usage of this wasm instance.
+///
+/// The returned object contains the number of reserved wasm memory pages (`memoryPages`)
+/// and the equivalent in bytes (`memoryBytes`),
+/// the number of bytes currently allocated (`allocatedBytes`),
+/// the peak of allocated bytes during the last solve (`peakAllocatedBytes`),
+/// and the estimated size of the caches used by the last solve (`cacheBytes`).
+/// Allocations are not counted in builds with the `minimal` feature,
+/// so `allocatedBytes` and `peakAllocatedBytes` are `null` there.
+#[wasm_bindgen]
+pub fn memory_stats() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&stats::memory_stats())?)
+}
+
+/// Report statistics of the calls to the JS callbacks made by the last solve.
+///
+/// `fetch_elm_json` is called at most once per package version during a solve,
+/// and `list_available_versions` at most once per package
+/// (unless caches are evicted because of the memory limit).
+/// The returned object contains the number of calls made to each callback
+/// (`fetchElmJsonCalls`, `listAvailableVersionsCalls`),
+/// and the number of duplicate calls that were avoided
+/// (`fetchElmJsonAvoided`, `listAvailableVersionsAvoided`).
+#[wasm_bindgen]
+pub fn solve_stats() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&stats::last_call_stats())?)
+}
+
+/// Report performance counters accumulated across solves since the last reset,
+/// to tell apart the time spent in the JS callbacks from the time spent solving.
+///
+/// The returned object contains the number of `solves`, the milliseconds spent
+/// in total (`totalMs`), in the JS callbacks (`callbackMs`) and in the solver itself
+/// (`solverMs`), the `bytesParsed` of elm.json documents, the number of callback
+/// `calls` made and avoided (with the same fields as `solve_stats`),
+/// and the `elmJsonCacheHitRate` and `versionsCacheHitRate` between 0 and 1.
+/// Times are measured with `performance.now()`.
+#[wasm_bindgen]
+pub fn perf_counters() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&stats::perf_counters())?)
+}
+
+/// Reset the performance counters reported by `perf_counters`.
+#[wasm_bindgen]
+pub fn reset_perf_counters() {
+    stats::reset_perf_counters();
+}
@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Reading, checking and editing `elm.json` files.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+
+use super::parse_project_elm_json;
+use crate::{constraints, diff, edit, lint, merge, utils};
+
+/// Check an `elm.json` for packages listed in several of its sections.
+///
+/// For an application, a package must appear only once among the direct and indirect
+/// dependencies and test dependencies. For a package, a dependency must not also be
+/// a test dependency. Duplicates with different versions are reported as contradictory.
+/// Return the list of findings, each with the elm.json `field` where the duplicate is,
+/// the `package`, and a `message`.
+#[wasm_bindgen]
+pub fn lint_elm_json(project_elm_json_str: &str) -> Result<JsValue, JsValue> {
+    let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    let findings = lint::duplicates(&project_elm_json);
+    Ok(serde_wasm_bindgen::to_value(&findings)?)
+}
+
+/// Fixed layout of the dependencies of an application.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LayoutFix {
+    /// Problems found, each with its fix.
+    findings: Vec<lint::Finding>,
+    /// JSON string of the fixed `elm.json`.
+    elm_json: String,
+}
+
+/// Fix the layout of the dependencies of an application `elm.json`.
+///
+/// Indirect entries duplicating direct ones are removed, keeping the direct version,
+/// and test dependencies already in the dependencies are removed.
+/// The optional `needed_direct` array lists packages directly used by the application,
+/// like the ones imported in its source code, which are moved from indirect to direct
+/// dependencies if needed.
+/// Return an object with the list of `findings`, each with the elm.json `field`,
+/// the `package` and a `message` describing the fix, and the fixed `elmJson` string.
+#[wasm_bindgen]
+pub fn fix_dependency_layout(
+    project_elm_json_str: &str,
+    // needed_direct: Vec<String>, can be omitted
+    needed_direct: JsValue,
+) -> Result<JsValue, JsValue> {
+    let app_config = match parse_project_elm_json(project_elm_json_str)? {
+        ProjectConfig::Application(app_config) => app_config,
+        ProjectConfig::Package(_) => {
+            return Err(utils::report_error(anyhow::anyhow!(
+                "The elm.json is not the one of an application"
+            )))
+        }
+    };
+    let needed_direct: Vec<String> = if needed_direct.is_undefined() || needed_direct.is_null() {
+        Vec::new()
+    } else {
+        serde_wasm_bindgen::from_value(needed_direct)?
+    };
+    let needed_direct = (needed_direct.iter())
+        .map(|p| Pkg::from_str(p))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to decode the needed direct dependencies")
+        .map_err(utils::report_error)?;
+    let (findings, fixed) = lint::fix_layout(&app_config, &needed_direct);
+    let elm_json = serde_json::to_string(&ProjectConfig::Application(fixed)).unwrap();
+    Ok(serde_wasm_bindgen::to_value(&LayoutFix {
+        findings,
+        elm_json,
+    })?)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const PROJECT_CONFIG_TS: &str = r#"
+export type Dependencies = { direct: Record<string, string>, indirect: Record<string, string> };
+export type ProjectConfig =
+  | {
+      type: "application",
+      "source-directories": string[],
+      "elm-version": string,
+      dependencies: Dependencies,
+      "test-dependencies": Dependencies,
+    }
+  | {
+      type: "package",
+      name: string,
+      summary: string,
+      license: string,
+      version: string,
+      "elm-version": string,
+      "exposed-modules": string[] | Record<string, string[]>,
+      dependencies: Record<string, string>,
+      "test-dependencies": Record<string, string>,
+    };
+"#;
+
+/// Parse an `elm.json` the same way the solver does.
+///
+/// Return an object with the same fields as the `elm.json`,
+/// with normalized versions and constraints,
+/// or an error if the solver would not accept that `elm.json`.
+/// Its shape is described by the `ProjectConfig` type of the TypeScript definitions.
+#[wasm_bindgen]
+pub fn parse_project_config(project_elm_json_str: &str) -> Result<JsValue, JsValue> {
+    let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    let categories = edit::ModuleCategories::read(project_elm_json_str);
+    let elm_json = edit::ElmJson::new(&project_elm_json).with_categories(&categories.categories);
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(serde::Serialize::serialize(&elm_json, &serializer)?)
+}
+
+/// Serialize an `elm.json` object, like the one returned by `parse_project_config`,
+/// back to the text of an `elm.json` file, written the way the elm compiler does.
+#[wasm_bindgen]
+pub fn serialize_project_config(project_config: JsValue) -> Result<String, JsValue> {
+    let project_elm_json: ProjectConfig = serde_wasm_bindgen::from_value(project_config.clone())?;
+    let categories: edit::ModuleCategories = serde_wasm_bindgen::from_value(project_config)?;
+    Ok(edit::to_elm_json_string(
+        &edit::ElmJson::new(&project_elm_json).with_categories(&categories.categories),
+    ))
+}
+
+/// Re-write an `elm.json` the way the elm compiler does.
+///
+/// Fields are in the order of the compiler, with 4 spaces indentation,
+/// dependencies are sorted by package name, categories of exposed modules keep their order,
+/// and the text ends with a new line, like an `elm.json` written by `elm` itself.
+#[wasm_bindgen]
+pub fn normalize_elm_json(project_elm_json_str: &str) -> Result<String, JsValue> {
+    let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    Ok(rewrite_elm_json(&project_elm_json, project_elm_json_str))
+}
+
+/// Text of an `elm.json` parsed from `original`, keeping its categories of exposed modules
+/// in their original order.
+fn rewrite_elm_json(project_elm_json: &ProjectConfig, original: &str) -> String {
+    let categories = edit::ModuleCategories::read(original);
+    edit::to_elm_json_string(
+        &edit::ElmJson::new(project_elm_json).with_categories(&categories.categories),
+    )
+}
+
+/// Section of an `elm.json` targeted by an edit.
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct SectionOptions {
+    test: Option<bool>,
+    indirect: Option<bool>,
+}
+
+fn parse_section(section: JsValue) -> Result<edit::Section, JsValue> {
+    let section: SectionOptions = if section.is_undefined() || section.is_null() {
+        SectionOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(section)?
+    };
+    Ok(edit::Section {
+        test: section.test.unwrap_or(false),
+        indirect: section.indirect.unwrap_or(false),
+    })
+}
+
+/// Add a dependency to an `elm.json`, or change its version or constraint.
+///
+/// For an application, `value` is the exact version of the package.
+/// For a package, it is a constraint like `"1.0.0 <= v < 2.0.0"`,
+/// or a version, allowing all versions up to the next major.
+/// The optional `section` object selects the `test` dependencies (default `false`),
+/// and the `indirect` dependencies of an application (default `false`).
+/// Return the text of the modified `elm.json`.
+#[wasm_bindgen]
+pub fn set_dependency(
+    project_elm_json_str: &str,
+    pkg: &str,
+    value: &str,
+    // section: { test?: boolean, indirect?: boolean }, can be omitted
+    section: JsValue,
+) -> Result<String, JsValue> {
+    let mut project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    let pkg = Pkg::from_str(pkg)
+        .with_context(|| format!("Invalid package name {}", pkg))
+        .map_err(utils::report_error)?;
+    edit::set_dependency(&mut project_elm_json, pkg, value, parse_section(section)?)
+        .map_err(utils::report_error)?;
+    Ok(rewrite_elm_json(&project_elm_json, project_elm_json_str))
+}
+
+/// Remove a dependency from an `elm.json`, direct or indirect.
+///
+/// The optional `section` object selects the `test` dependencies (default `false`).
+/// Return the text of the modified `elm.json`, or an error if the package was not there.
+#[wasm_bindgen]
+pub fn remove_dependency(
+    project_elm_json_str: &str,
+    pkg: &str,
+    // section: { test?: boolean }, can be omitted
+    section: JsValue,
+) -> Result<String, JsValue> {
+    let mut project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    let pkg = Pkg::from_str(pkg)
+        .with_context(|| format!("Invalid package name {}", pkg))
+        .map_err(utils::report_error)?;
+    let section = parse_section(section)?;
+    if !edit::remove_dependency(&mut project_elm_json, &pkg, section.test) {
+        return Err(utils::report_error(anyhow::anyhow!(
+            "{} is not a dependency of this elm.json",
+            pkg
+        )));
+    }
+    Ok(rewrite_elm_json(&project_elm_json, project_elm_json_str))
+}
+
+/// Check that the test dependencies of an `elm.json` are consistent with its dependencies.
+///
+/// For an application, a package listed in both sections must have the same version.
+/// For a package, the constraints of a package listed in both sections must intersect.
+/// Return the list of conflicts found, each with the `package`,
+/// and its version (or constraint) in `dependencies` and in `testDependencies`.
+#[wasm_bindgen]
+pub fn check_test_consistency(project_elm_json_str: &str) -> Result<JsValue, JsValue> {
+    let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    let conflicts = lint::test_consistency(&project_elm_json);
+    Ok(serde_wasm_bindgen::to_value(&conflicts)?)
+}
+
+/// Compare the dependencies of two `elm.json`, like before and after a change.
+///
+/// Return the list of changes, sorted by section and package, each with the `package`,
+/// its `section` (`"dependencies"` or `"test-dependencies"` for a package,
+/// and for an application, `"dependencies.direct"`, `"dependencies.indirect"`,
+/// `"test-dependencies.direct"` or `"test-dependencies.indirect"`),
+/// the `kind` of change (`"added"`, `"removed"` or `"changed"`),
+/// and its version or constraint `before` and `after` the change, when there is one.
+/// A package moving between sections is removed from one and added to the other.
+#[wasm_bindgen]
+pub fn diff_projects(elm_json_a: &str, elm_json_b: &str) -> Result<JsValue, JsValue> {
+    let before = parse_project_elm_json(elm_json_a)?;
+    let after = parse_project_elm_json(elm_json_b)?;
+    let changes = diff::diff_projects(&before, &after);
+    Ok(serde_wasm_bindgen::to_value(&changes)?)
+}
+
+/// Merge two dependency sections of `elm.json` files, intersecting their constraints.
+///
+/// Each section is an object mapping packages to a constraint, like the `dependencies`
+/// of a package `elm.json`, or to a version, like the `direct` dependencies of an application.
+/// Constraints have the syntax of additional constraints.
+/// Return an object with the merged `dependencies`, mapping each package to the
+/// intersection of its constraints, and the `conflicts`, with the `package`
+/// and its constraints in `a` and in `b`, for the packages whose constraints do not intersect.
+/// Returned constraints have the syntax of additional constraints, like
+/// `"1.0.0 <= v < 2.0.0 && != 1.3.0"`, or an array of constraints for disjoint intervals.
+#[wasm_bindgen]
+pub fn merge_dependencies(a: JsValue, b: JsValue) -> Result<JsValue, JsValue> {
+    let parse = |section: JsValue| -> Result<BTreeMap<Pkg, Range<SemVer>>, JsValue> {
+        let section: BTreeMap<String, String> = serde_wasm_bindgen::from_value(section)?;
+        (section.iter())
+            .map(|(pkg, value)| {
+                let range = match SemVer::from_str(value) {
+                    Ok(version) => Range::exact(version),
+                    Err(_) => constraints::parse(value)?.0,
+                };
+                Ok((Pkg::from_str(pkg)?, range))
+            })
+            .collect::<anyhow::Result<_>>()
+            .context("Failed to decode the dependencies")
+            .map_err(utils::report_error)
+    };
+    let merged = merge::merge_dependencies(&parse(a)?, &parse(b)?);
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(serde::Serialize::serialize(&merged, &serializer)?)
+}
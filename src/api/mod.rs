@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Functions exported to JS, grouped by topic.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, ApplicationConfig, Pkg, ProjectConfig};
+use wasm_bindgen::prelude::*;
+
+use crate::provider::JsProvider;
+use crate::{constraints, edit, options, pipeline, utils};
+
+mod analysis;
+mod diagnostics;
+mod elm_json;
+mod solution;
+mod solve;
+mod versions;
+
+pub use analysis::*;
+pub use diagnostics::*;
+pub use elm_json::*;
+pub use solution::*;
+pub use solve::*;
+pub use versions::*;
+
+/// Options and JS callbacks of an export calling the package registry.
+struct SolveContext {
+    options: options::SolveOptions,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+}
+
+impl SolveContext {
+    /// Read the options of an export, which can be `undefined` or `null`.
+    fn new(
+        options: JsValue,
+        js_fetch_elm_json: js_sys::Function,
+        js_list_available_versions: js_sys::Function,
+    ) -> Result<Self, JsValue> {
+        Ok(Self {
+            options: options::SolveOptions::from_js(options)?,
+            js_fetch_elm_json,
+            js_list_available_versions,
+        })
+    }
+
+    /// Read the options of an export, with the callbacks of a provider object.
+    fn with_provider(options: JsValue, provider: JsValue) -> Result<Self, JsValue> {
+        let mut options = options::SolveOptions::from_js(options)?;
+        let (js_fetch_elm_json, js_list_available_versions) = options.use_provider(provider)?;
+        Ok(Self {
+            options,
+            js_fetch_elm_json,
+            js_list_available_versions,
+        })
+    }
+
+    /// Run an export with the log level of the options, restored when it returns.
+    fn run<T>(self, f: impl FnOnce(Self) -> Result<T, JsValue>) -> Result<T, JsValue> {
+        let _log_level = utils::LogLevelGuard::set(self.options.log_level.map(|l| l.filter()));
+        f(self)
+    }
+
+    /// Dependency provider calling the JS callbacks, configured with the options.
+    fn provider(&self) -> JsProvider {
+        JsProvider::new(
+            self.js_fetch_elm_json.clone(),
+            self.js_list_available_versions.clone(),
+            &self.options,
+        )
+    }
+
+    /// Dependency provider for the solve of a project,
+    /// trying first its versions with the `preferProjectVersions` option.
+    fn project_provider(&self, project_elm_json: &ProjectConfig) -> JsProvider {
+        pipeline::prefer_project_versions(self.provider(), &self.options, project_elm_json)
+    }
+}
+
+/// Run an export calling the package registry through the JS callbacks,
+/// with the options read and their log level applied.
+fn with_solve_context<T>(
+    options: JsValue,
+    js_fetch_elm_json: js_sys::Function,
+    js_list_available_versions: js_sys::Function,
+    f: impl FnOnce(SolveContext) -> Result<T, JsValue>,
+) -> Result<T, JsValue> {
+    SolveContext::new(options, js_fetch_elm_json, js_list_available_versions)?.run(f)
+}
+
+/// Load the elm.json of the project to solve.
+fn parse_project_elm_json(project_elm_json_str: &str) -> Result<ProjectConfig, JsValue> {
+    serde_json::from_str(project_elm_json_str)
+        .context("Failed to decode the elm.json")
+        .map_err(utils::report_error)
+}
+
+/// Load a solution returned by `solve_deps`.
+fn parse_solution(solution_str: &str) -> Result<AppDependencies, JsValue> {
+    serde_json::from_str(solution_str)
+        .context("Failed to decode the solution")
+        .map_err(utils::report_error)
+}
+
+/// Parse additional constraints, provided as a JS object.
+fn parse_js_constraints(
+    additional_constraints_str: JsValue,
+) -> Result<Vec<(Pkg, Constraint)>, JsValue> {
+    let additional_constraints: HashMap<String, constraints::AdditionalConstraint> =
+        serde_wasm_bindgen::from_value(additional_constraints_str)?;
+    pipeline::parse_additional_constraints(additional_constraints).map_err(utils::report_error)
+}
+
+/// Convert an application configuration into its elm.json, in the output format.
+fn app_config_to_js(
+    app_config: ApplicationConfig,
+    output: options::OutputFormat,
+) -> Result<JsValue, JsValue> {
+    output.to_js(&edit::ElmJson::new(&ProjectConfig::Application(app_config)))
+}
@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conversions and checks of the solutions returned by the solver.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::project_config::Pkg;
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+
+use super::{app_config_to_js, parse_project_elm_json, parse_solution, with_solve_context};
+use crate::{formats, lint, options, sbom, utils};
+
+/// Convert a solution into the dependencies of a package `elm.json`.
+///
+/// The solution is the JSON string returned by `solve_deps`.
+/// Each direct dependency `X.Y.Z` becomes the constraint `X.Y.Z <= v < (X+1).0.0`,
+/// except for `elm/core` whose lower bound is `X.0.0`.
+/// Indirect dependencies are dropped since packages only list their direct dependencies.
+/// Return the JSON string of the `dependencies` object.
+#[wasm_bindgen]
+pub fn solution_to_constraints(solution_str: &str) -> Result<JsValue, JsValue> {
+    let solution = parse_solution(solution_str)?;
+    let constraints = formats::package_constraints(&solution);
+    Ok(JsValue::from_str(
+        &serde_json::to_string(&constraints).unwrap(),
+    ))
+}
+
+/// Metadata used to draw the dependency graph of a solution.
+#[derive(Default, serde::Deserialize)]
+#[serde(default)]
+struct DotMetadata {
+    /// Dependencies of each package, like the `graph` field of a solution.
+    dependencies: Option<BTreeMap<String, Vec<String>>>,
+    /// Packages that are only test dependencies.
+    test: Option<BTreeSet<String>>,
+}
+
+/// Convert a solution into a Graphviz DOT document of its dependency graph.
+///
+/// The solution is the JSON string returned by `solve_deps`.
+/// The optional `metadata` object may contain the `dependencies` of each package,
+/// like the `graph` field added to the solution with the `graph` option,
+/// and the array of packages that are only `test` dependencies.
+/// Direct dependencies are drawn in bold and test dependencies in gray.
+#[wasm_bindgen]
+pub fn solution_to_dot(solution_str: &str, metadata: JsValue) -> Result<String, JsValue> {
+    let solution = parse_solution(solution_str)?;
+    let metadata: DotMetadata = if metadata.is_undefined() || metadata.is_null() {
+        DotMetadata::default()
+    } else {
+        serde_wasm_bindgen::from_value(metadata)?
+    };
+    Ok(formats::dot(
+        &solution,
+        &metadata.dependencies.unwrap_or_default(),
+        &metadata.test.unwrap_or_default(),
+    ))
+}
+
+/// Convert a solution into a software bill of materials (SBOM).
+///
+/// The solution is the JSON string returned by `solve_deps`,
+/// and `format` is either `"cyclonedx"` for CycloneDX 1.5 or `"spdx"` for SPDX 2.3,
+/// both in JSON. The optional `metadata` object may contain the `name` of the project,
+/// the `dependencies` of each package and the packages of each license,
+/// like the `graph` and `licenses` fields added to the solution with those options,
+/// the SHA-1 `hashes` of the package zipballs, as in the `downloads` field,
+/// and for SPDX, the `created` time (now by default) and the document `namespace`.
+/// Return the JSON string of the document.
+#[wasm_bindgen]
+pub fn solution_to_sbom(
+    solution_str: &str,
+    metadata: JsValue,
+    format: JsValue,
+) -> Result<String, JsValue> {
+    let solution = parse_solution(solution_str)?;
+    let mut metadata: sbom::SbomMetadata = if metadata.is_undefined() || metadata.is_null() {
+        sbom::SbomMetadata::default()
+    } else {
+        serde_wasm_bindgen::from_value(metadata)?
+    };
+    let format: sbom::SbomFormat = serde_wasm_bindgen::from_value(format)?;
+    if metadata.created.is_none() {
+        // Without the milliseconds, as required by SPDX.
+        let now = String::from(js_sys::Date::new_0().to_iso_string());
+        metadata.created = Some(format!("{}Z", &now[..19]));
+    }
+    let document = sbom::sbom(&solution, &metadata, format);
+    Ok(serde_json::to_string_pretty(&document).unwrap())
+}
+
+/// Convert a solution into the `elm.json` of the tests application generated by elm-test-rs.
+///
+/// The solution is the JSON string returned by `solve_deps` with `use_test`,
+/// and `source_directories` is the array of source directories of the tests application.
+/// All packages of the solution become dependencies of the application,
+/// keeping their direct or indirect status, and test dependencies are left empty.
+#[wasm_bindgen]
+pub fn elm_test_rs_config(
+    solution_str: &str,
+    source_directories: JsValue,
+) -> Result<JsValue, JsValue> {
+    let solution = parse_solution(solution_str)?;
+    let source_directories: Vec<String> = serde_wasm_bindgen::from_value(source_directories)?;
+    let elm_version = SemVer::from(formats::ELM_TEST_RS_ELM_VERSION);
+    let app_config = formats::tests_application(solution, elm_version, source_directories);
+    app_config_to_js(app_config, options::OutputFormat::Compact)
+}
+
+/// Derive the `elm.json` of a sub-application sharing the exact versions of a parent solution,
+/// like an example application in the monorepo of a main application.
+///
+/// The solution is the JSON string returned by `solve_deps` for the parent application,
+/// and `direct` is the array of the direct dependencies of the sub-application,
+/// which must all be in the solution. Their dependencies, found with `fetch_elm_json`,
+/// become indirect dependencies, with the versions of the solution.
+/// The source directories are `src`, and the elm version is the `elmVersion` option,
+/// 0.19.1 by default.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn derive_application(
+    solution_str: &str,
+    direct: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let options = &context.options;
+            let solution = parse_solution(solution_str)?;
+            let direct: Vec<String> = serde_wasm_bindgen::from_value(direct)?;
+            let direct = (direct.iter())
+                .map(|pkg| Pkg::from_str(pkg))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to decode the direct dependencies")
+                .map_err(utils::report_error)?;
+            let provider = context.provider();
+            let app_config = formats::sub_application(
+                &solution,
+                &direct,
+                options.target_elm_version(),
+                |pkg, version| {
+                    let pkg_config = provider.fetch_elm_json(pkg, version)?;
+                    Ok(pkg_config.dependencies.keys().cloned().collect())
+                },
+            )
+            .map_err(utils::report_error_message)?;
+            app_config_to_js(app_config, options.output.unwrap_or_default())
+        },
+    )
+}
+
+/// Check that a solution satisfies every constraint, without solving anything.
+///
+/// The `solution` is a JSON string like the ones returned by `solve_deps`,
+/// and `elm_jsons` is an object mapping each package and version to its elm.json string,
+/// like `{ "elm/core": { "1.0.5": "{...}" } }`, as the `packages` of `generate_registry`.
+/// The constraints of the project and of each package of the solution are checked,
+/// and direct dependencies must be the ones of the project.
+/// Test dependencies of the project are only checked if they are in the solution.
+/// This is an independent oracle to check the solver, or wrappers around it, in property tests.
+/// Return an object with `valid` set to `true` if there is no violation,
+/// and the list of `violations`, each with the `package` concerned,
+/// what it is `requiredBy` (`"project"` or a package version like `"elm/http@2.0.0"`),
+/// and a `message`.
+#[wasm_bindgen]
+pub fn check_solution_valid(
+    project_elm_json_str: &str,
+    solution_str: &str,
+    elm_jsons: JsValue,
+) -> Result<JsValue, JsValue> {
+    let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+    let solution = parse_solution(solution_str)?;
+    let elm_jsons: BTreeMap<Pkg, BTreeMap<SemVer, String>> =
+        serde_wasm_bindgen::from_value(elm_jsons)?;
+    let violations = lint::solution_violations(&project_elm_json, &solution, |pkg, version| {
+        let elm_json = (elm_jsons.get(pkg).and_then(|vs| vs.get(&version)))
+            .ok_or_else(|| "No elm.json was provided".to_string())?;
+        serde_json::from_str(elm_json)
+            .map(Rc::new)
+            .map_err(|err| format!("Invalid elm.json: {}", err))
+    });
+    Ok(serde_wasm_bindgen::to_value(&SolutionValidity {
+        valid: violations.is_empty(),
+        violations,
+    })?)
+}
+
+/// Result of `check_solution_valid`.
+#[derive(serde::Serialize)]
+struct SolutionValidity {
+    valid: bool,
+    violations: Vec<lint::Violation>,
+}
@@ -0,0 +1,646 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Solving the dependencies of projects.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+
+use super::{
+    app_config_to_js, parse_js_constraints, parse_project_elm_json, with_solve_context,
+    SolveContext,
+};
+use crate::pipeline::{self, SolveInput};
+use crate::{constraints, options, provider, report, solver, utils};
+
+/// Solve dependencies for the provided `elm.json`.
+///
+/// Include also test dependencies if `use_test` is `true`.
+/// It is possible to add additional constraints, as an object mapping packages to constraints.
+/// On top of the elm.json syntax, like `"1.0.0 <= v < 2.0.0"`, a version can be excluded
+/// with `"!= 1.3.0"`, and constraints combined with `&&`,
+/// like `"1.0.0 <= v < 2.0.0 && != 1.3.0"`.
+/// A constraint can also be an array of constraints whose ranges are unioned,
+/// like `["1.0.0 <= v < 1.3.0", "1.4.0 <= v < 2.0.0"]`.
+/// The caller is responsible to provide implementations to be able to fetch the `elm.json` of
+/// dependencies, as well as to list existing versions (in prefered order) for a given package.
+/// The `elm.json` can be returned as a JSON string, as an already parsed object,
+/// or as UTF-8 bytes in a `Uint8Array`, like the `Buffer` read by `fs.readFileSync`,
+/// and versions as an array of strings or as a string with one version per line.
+/// Versions are never listed for packages constrained to an exact version,
+/// such as the direct dependencies of an application,
+/// their `elm.json` is fetched directly instead.
+///
+/// The solve is deterministic: given the same arguments and the same responses of the callbacks,
+/// the same solution is always returned, whatever the machine.
+/// When several packages are equally good candidates for the next decision,
+/// the first one in alphabetical order is picked.
+///
+/// The last `options` argument is an optional object with the following optional fields:
+///  - `memoryLimit`: soft cap on the heap memory, in bytes.
+///    Caches are evicted when going over it, and the solve fails if that is not enough,
+///    with an error starting with "Memory budget exceeded".
+///    Allocations are not counted in builds with the `minimal` feature, where it has no effect.
+///  - `maxVersionsPerPackage`: only explore the N most prefered versions of each package at first.
+///    If there is no solution with those, the solve falls back to all versions.
+///  - `packagePriorities`: object mapping packages to a priority number (0 by default).
+///    When several packages are undecided, those with the highest priority are decided first.
+///  - `packagePriority`: function `(pkg) => number` overriding `packagePriorities`.
+///    It is called at most once per package.
+///  - `chooseVersion`: function `(pkg, candidateVersions) => chosenVersion`,
+///    called when the solver must pick among several admissible versions,
+///    given in prefered order. Returning `null` or `undefined` picks the first one.
+///  - `hasLocally`: function `(pkg, version) => boolean` telling if a package version
+///    is available locally, without download.
+///  - `preferLocal`: if `true`, versions available locally are tried first,
+///    and versions requiring a download are only picked when necessary.
+///    This requires the `hasLocally` function.
+///  - `preferProjectVersions`: if `true`, versions already recorded in the application `elm.json`
+///    are tried first, to minimize changes, without preventing changes when required.
+///  - `twoPhaseTest`: if `true` when solving with test dependencies,
+///    the normal dependencies are solved first and frozen,
+///    then test dependencies are solved on top, like the elm compiler does.
+///  - `licenses`: if `true`, the solution has an additional `licenses` field,
+///    mapping each license to the resolved packages using it.
+///  - `summary`: if `true`, the solution has an additional `summary` field, with the
+///    `packageCount`, `directCount`, `indirectCount`, the `maxDepth` of dependency chains,
+///    and the `elmVersion` range compatible with all packages.
+///  - `freshness`: if `true`, the solution has an additional `freshness` field,
+///    mapping each package to its `current` and `latest` versions,
+///    the number of versions published after the current one (`versionsBehind`),
+///    and the `timeBehind` the latest release, if their dates are in `releaseDates`.
+///    This may list the versions of packages that were not listed during the solve.
+///  - `releaseDates`: object mapping packages to the release dates of their versions,
+///    like `{ "elm/core": { "1.0.5": 1583342566 } }`, as provided by the package registry.
+///  - `watchList`: if `true`, the solution has an additional `watchList` field,
+///    with the packages whose future releases could change the solution.
+///    Those are all the packages reachable from the project under its constraints,
+///    exploring every version allowed, so this may fetch many more `elm.json`.
+///  - `downloads`: if `true`, the solution has an additional `downloads` field,
+///    listing for each package its `package` name, `version`, and the URLs of its
+///    `endpoint` (with the zipball URL and hash), `elmJson` and `docs` files.
+///  - `registryUrl`: address of the package registry used for `downloads`,
+///    `https://package.elm-lang.org` by default.
+///  - `fetchEndpoint`: function `(pkg, version) => { url, hash }` returning the content
+///    of the `endpoint.json` of a package version. When provided, `downloads` entries
+///    also have the `url` and `hash` of the package zipball.
+///    It is called at most once per package version, even with both `downloads` and `provenance`.
+///  - `installPlan`: if `true`, the solution has an additional `install` field,
+///    listing the `package` and `version` of the packages missing locally,
+///    with dependencies before their dependents. This requires the `hasLocally` function.
+///  - `graph`: if `true`, the solution has an additional `graph` field,
+///    mapping each package to the array of its dependencies.
+///  - `provenance`: if `true`, the solution has an additional `provenance` field, mapping
+///    each package to the source of its `metadata` and of its `artifact`. The metadata
+///    comes from an `"override"` elm.json, or from `fetch_elm_json`, which may report where it
+///    found the elm.json by returning `{ elmJson, source }`, like `{ elmJson, source: "github" }`.
+///    Otherwise it is `"fetchElmJson"`. The artifact comes from the `origin` of an override,
+///    the `url` returned by `fetchEndpoint`, or the registry (`registryUrl`).
+///  - `elmCompatibility`: if `true`, the solution has an additional `elmCompatibility` field,
+///    with the `elmVersion` range compatible with all packages, whether it is `empty`,
+///    and whether it `allowsProject`, the elm version declared by the project.
+///    A warning is logged when the solution compiles with no elm version, or not the project one.
+///  - `pins`: object mapping packages to a version they are forced to, like `{ "elm/json": "1.1.3" }`.
+///    Pinned packages are only part of the solution if something depends on them.
+///    When a pin makes the resolution impossible, the error starts with
+///    "The pinned versions ... make the resolution impossible", naming the pins involved.
+///  - `overrides`: object mapping packages replaced by another origin (git, vendored, fork)
+///    to an object with their fixed `version`, their `origin`, and optionally their `elmJson`
+///    string, which is otherwise fetched with `js_fetch_elm_json`.
+///    Overridden versions are never listed with `js_list_available_versions`,
+///    and the solution has an additional `overrides` field mapping
+///    the overridden packages of the solution to their origin.
+///  - `output`: format of the returned solution, a compact JSON string by default (`"compact"`),
+///    `"pretty"` to write it like the elm compiler writes elm.json files,
+///    with 4 spaces indentation, so it can be diffed cleanly against them,
+///    or `"object"` to return a JS object instead of a JSON string.
+///  - `errorTree`: if `true`, when there is no solution, the thrown error is an `Error`
+///    whose `message` and `report` field are the text report, and whose `tree` field
+///    is the derivation tree explaining the conflict. Each node of the tree has a `kind`,
+///    one of `"notRoot"`, `"noVersions"`, `"unavailableDependencies"`,
+///    `"fromDependencyOf"` (with the `dependency` and `dependencyRange` of the `package`),
+///    or `"derived"`, with the `terms` of the incompatibility and its two causes
+///    `cause1` and `cause2`.
+///    The error also has a `rootCauses` field, a short summary of the conflict, listing
+///    `"incompatible"` pairs of `requirements` on the same `package`, and `"noVersions"`
+///    ranges of a `package` without any version, each with a one line `message`.
+///  - `maxReportLines`: maximum number of lines of the report when there is no solution.
+///    Longer reports keep their first lines and their conclusion, with a line summarizing
+///    the others, like "… and 37 more incompatibilities involving elm/json (20)".
+///  - `reportFormat`: format of the report when there is no solution, `"text"` by default.
+///    With `"sections"`, each intermediate incompatibility of the report is explained
+///    in a section titled with its conflicting packages, and the conclusion comes last.
+///    With `"markdown"`, those sections are collapsible `<details>` elements.
+///    Only `"text"` reports are shortened by `maxReportLines`.
+///  - `suggestVersions`: if `true`, when there is no solution, the report ends with
+///    the versions closest to the requested ones of the project dependencies involved
+///    in the conflict that would make the resolution possible, if any.
+///    Each candidate version is checked with another solve, so this may take a while.
+///    With `errorTree`, those are also in the `hints` field of the error, with the `package`,
+///    its `requested` constraint and the working `versions`.
+///  - `objective`: search for the best solution according to an objective,
+///    `"recency"` for the fewest newer versions than the chosen ones over all packages,
+///    or `"fewestPackages"` for the fewest packages. The search excludes the chosen versions
+///    one at a time, solving again, and keeps the solutions with a better score.
+///  - `score`: function `(solution) => number` giving the score of a solution,
+///    higher is better, used instead of `objective`.
+///  - `budget`: maximum number of additional solves when searching for the best solution,
+///    20 by default.
+///  - `seed`: integer randomizing the tie-breaking of decisions, to sample the space of
+///    valid solutions instead of always returning the same one. Packages with the same
+///    priority are decided in a pseudo-random order, and versions are picked pseudo-randomly
+///    among the admissible ones, unless `chooseVersion` is provided.
+///    The same seed always gives the same solution.
+///  - `elmVersion`: version of the Elm compiler targeted, like `"0.19.1"`.
+///    Any version is accepted, so future compiler releases and forks are supported.
+///    Package versions whose `elm-version` range does not contain it are never picked,
+///    and are reported with unavailable dependencies if that makes the resolution impossible.
+///  - `allowedLicenses`: array of the only licenses allowed for the packages of the solution,
+///    as written in their elm.json, like `["BSD-3-Clause", "MIT"]`.
+///    Any license is allowed if empty or omitted.
+///  - `deniedLicenses`: array of licenses never allowed for the packages of the solution.
+///  - `licensePolicy`: `"fail"` (the default) to fail with an error starting with
+///    "License policy violation" when the solution has packages with a license not allowed,
+///    or `"warn"` to log warnings instead, with an additional `licenseViolations` field
+///    in the solution. Each violation names the `package`, its `version`, its `license`,
+///    and the `path` of dependencies pulling it in, from a direct dependency of the project.
+///  - `advisories`: array of security advisories, like
+///    `{ pkg: "elm/http", affectedRange: "2.0.0 <= v < 2.0.1", severity: "high", id: "CVE-2021-1234" }`,
+///    where `affectedRange` has the syntax of additional constraints.
+///    The solution has an additional `vulnerabilities` field, listing the `package`, `version`,
+///    `id`, `severity` and `affectedRange` of each affected package of the solution.
+///  - `advisoryPolicy`: `"avoid"` (the default) to never pick the affected versions,
+///    or `"report"` to pick them like any other version and only report them.
+///  - `approve`: function `(pkg, version) => boolean` called before the solver picks
+///    a package version, to encode governance rules like blocked versions or embargoed packages.
+///    Versions not approved are never picked. It is called at most once per package version.
+///  - `allowedAuthors`: array of the only authors whose packages may be part of the solution,
+///    like `["elm", "elm-explorations"]`. Any author is allowed if empty or omitted.
+///  - `deniedAuthors`: array of authors whose packages must never be part of the solution.
+///    When the solution needs a forbidden package, the solve fails with an error starting
+///    with "Policy violation", listing the chain of dependents pulling it in.
+///    The project is not checked, only its dependencies.
+///  - `logLevel`: log level for this call only, one of `"off"`, `"error"`, `"warn"`,
+///    `"info"`, `"debug"` or `"trace"`. The global level is restored afterwards.
+///  - `signal`: an `AbortSignal`, checked at each step of the solve,
+///    which then fails with an error starting with "The solve was aborted".
+///    Since the solver is synchronous, the signal can only be aborted from the callbacks,
+///    or between the attempts of the promise-based API.
+///  - `context`: any value, passed as an additional last argument to every callback,
+///    like `js_fetch_elm_json(pkg, version, context)`, to share per-solve state
+///    with plain functions instead of closures.
+///  - `thisArg`: value used as `this` when calling the callbacks, `null` by default,
+///    so they can be methods of an object relying on `this`.
+///  - `retries`: maximum number of retries of `js_fetch_elm_json` and
+///    `js_list_available_versions` when they fail transiently, 0 by default.
+///    A transient failure is signaled by throwing an error with a `retryable` field
+///    set to `true`, or by returning an object `{ retryAfterMs }` instead of the result.
+///    Retries wait `retryAfterMs` milliseconds if provided, or an exponential backoff
+///    starting at 100ms, except where blocking is not allowed (browser main thread).
+///  - `maxDocumentSize`: maximum size in bytes of an `elm.json` returned by the callbacks,
+///    1 MiB by default. Larger documents, invalid ones, or those of another package version
+///    than requested fail the solve with an error starting with "Invalid elm.json returned for",
+///    followed by the package and version.
+///  - `fetchElmJsonBatch`: function `(requests) => string[]` used instead of
+///    `js_fetch_elm_json`, receiving an array of `[pkg, version]` pairs
+///    and returning their elm.json in the same order.
+///    Each time an elm.json is needed, the next versions of the package in prefered order
+///    are requested with it, since they are the ones tried next if it is rejected.
+///  - `batchSize`: maximum number of elm.json requested per call of `fetchElmJsonBatch`,
+///    10 by default.
+///  - `listAvailableVersionsBatch`: function `(pkgs) => ({ [pkg]: versions })` used instead of
+///    `js_list_available_versions`, receiving an array of packages and returning an object
+///    mapping each of them to its available versions. At each decision, all the candidate
+///    packages not listed yet are requested at once.
+///  - `relaxedPackageNames`: accept package names without author, like `pkg`,
+///    for private registries with other naming conventions, `false` by default.
+///    They are passed unchanged to the JS callbacks and returned unchanged in the solution,
+///    but are shown with a leading `/` in error reports.
+///    Their author is the empty string `""`, which must be listed in `allowedAuthors`
+///    to allow them when that option is set, and can be listed in `deniedAuthors`.
+#[wasm_bindgen]
+pub fn solve_deps(
+    project_elm_json_str: &str,
+    use_test: bool,
+    // additional_constraints_str: &HashMap<String, Constraint>,
+    additional_constraints_str: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            solve_project(
+                project_elm_json_str,
+                use_test,
+                additional_constraints_str,
+                context,
+            )
+        },
+    )
+}
+
+/// Solve dependencies with a provider object, whose methods are called with it as `this`.
+///
+/// The `provider` object must have the `fetchElmJson(pkg, version)` and
+/// `listAvailableVersions(pkg)` methods, and may have the `hasLocally`, `chooseVersion`,
+/// `packagePriority`, `fetchEndpoint` and `approve` methods, used if not given in the options.
+/// This enables class instances keeping their state in their fields.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn solve_deps_with_provider(
+    project_elm_json_str: &str,
+    use_test: bool,
+    // additional_constraints_str: &HashMap<String, Constraint>,
+    additional_constraints_str: JsValue,
+    // provider: { fetchElmJson, listAvailableVersions, ... }
+    provider: JsValue,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    SolveContext::with_provider(options, provider)?.run(|context| {
+        solve_project(
+            project_elm_json_str,
+            use_test,
+            additional_constraints_str,
+            context,
+        )
+    })
+}
+
+/// Solve a project like `solve_deps`, with the solve configured by the options.
+fn solve_project(
+    project_elm_json_str: &str,
+    use_test: bool,
+    additional_constraints_str: JsValue,
+    context: SolveContext,
+) -> Result<JsValue, JsValue> {
+    let options = &context.options;
+    solve_project_with(
+        project_elm_json_str,
+        use_test,
+        additional_constraints_str,
+        &context,
+        |provider, input| pipeline::solve_with_options(provider, options, input),
+    )
+}
+
+/// Parse the input of a solve and configure its provider with the options,
+/// solve with the given function, and convert the result with `solution_to_js`,
+/// grouping the logs of each phase.
+fn solve_project_with(
+    project_elm_json_str: &str,
+    use_test: bool,
+    additional_constraints_str: JsValue,
+    context: &SolveContext,
+    solve: impl FnOnce(
+        &provider::JsProvider,
+        SolveInput,
+    ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>,
+) -> Result<JsValue, JsValue> {
+    let options = &context.options;
+    let parsing = utils::LogPhase::start("parsing");
+    let (project_elm_json, additional_constraints) =
+        parse_solve_input(project_elm_json_str, additional_constraints_str, options)?;
+    drop(parsing);
+    let fetching = utils::LogPhase::start("fetching");
+    let provider = pipeline::configure_provider(context.provider(), options, &project_elm_json)
+        .map_err(utils::report_error)?;
+    drop(fetching);
+    let resolution = utils::LogPhase::start("resolution");
+    let input = (&project_elm_json, use_test, &additional_constraints[..]);
+    let result = solve(&provider, input);
+    drop(resolution);
+    let _reporting = utils::LogPhase::start("reporting");
+    solution_to_js(result, &provider, options, input)
+}
+
+/// Solve dependencies with the least overhead per call, for hosts issuing many solves.
+///
+/// It does the same as `solve_deps` without options, but skips everything not needed
+/// for the solve itself: no statistics are recorded for `solve_stats` and `memory_stats`,
+/// and errors are neither logged nor formatted with their backtrace,
+/// only with their chain of causes.
+/// It does not require calling `init()` first.
+///
+/// Arguments are the same than for `solve_deps`, without the options.
+#[wasm_bindgen]
+pub fn solve_deps_quiet(
+    project_elm_json_str: &str,
+    use_test: bool,
+    // additional_constraints_str: &HashMap<String, Constraint>,
+    additional_constraints_str: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+) -> Result<String, JsValue> {
+    let quiet_error = |err: anyhow::Error| JsValue::from_str(&format!("{:#}", err));
+    let project_elm_json: ProjectConfig = serde_json::from_str(project_elm_json_str)
+        .context("Failed to decode the elm.json")
+        .map_err(quiet_error)?;
+    let additional_constraints: HashMap<String, constraints::AdditionalConstraint> =
+        serde_wasm_bindgen::from_value(additional_constraints_str)?;
+    let additional_constraints =
+        pipeline::parse_additional_constraints(additional_constraints).map_err(quiet_error)?;
+    let options = options::SolveOptions::default();
+    let provider =
+        provider::JsProvider::new(js_fetch_elm_json, js_list_available_versions, &options);
+    let solution = solver::solve_deps_js(
+        &project_elm_json,
+        use_test,
+        &additional_constraints,
+        &provider,
+        false,
+    )
+    .map_err(|err| quiet_error(pipeline::handle_pubgrub_error(err)))?;
+    Ok(serde_json::to_string(&solution).unwrap())
+}
+
+/// Solve only the test dependencies for the provided `elm.json`.
+///
+/// For an application, the versions recorded in its `dependencies` are kept fixed.
+/// For a package, its normal dependencies are solved first and kept fixed.
+/// The solution only contains the test dependencies,
+/// with the `direct` and `indirect` fields of the `test-dependencies` section.
+/// Additional constraints are considered as test dependencies.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn solve_test_deps(
+    project_elm_json_str: &str,
+    // additional_constraints_str: &HashMap<String, Constraint>,
+    additional_constraints_str: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            solve_project_with(
+                project_elm_json_str,
+                true,
+                additional_constraints_str,
+                &context,
+                |provider, (project_elm_json, _, additional_constraints)| {
+                    pipeline::tracked_solve(provider, || {
+                        solver::solve_test_deps_js(
+                            project_elm_json,
+                            additional_constraints,
+                            provider,
+                        )
+                    })
+                },
+            )
+        },
+    )
+}
+
+/// Solve the dependencies needed to compile and run the tests of a package.
+///
+/// The package dependencies and test dependencies are resolved together,
+/// and the result is an application `elm.json` where they all are dependencies,
+/// like the tests runner application generated by elm-test-rs.
+/// Its source directories are `src` and `tests`, and its elm version is the `elmVersion`
+/// option (0.19.1 by default) if allowed by the package, otherwise the lowest version allowed.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn solve_package_tests(
+    project_elm_json_str: &str,
+    // additional_constraints_str: &HashMap<String, Constraint>,
+    additional_constraints_str: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            if let ProjectConfig::Application(_) = project_elm_json {
+                return Err(utils::report_error(anyhow::anyhow!(
+                    "The elm.json is not the one of a package"
+                )));
+            }
+            let additional_constraints = parse_js_constraints(additional_constraints_str)?;
+            let options = &context.options;
+            let provider = context.project_provider(&project_elm_json);
+            let result = pipeline::tracked_solve(&provider, || {
+                solver::solve_tests_config_js(
+                    &project_elm_json,
+                    &additional_constraints,
+                    options.target_elm_version(),
+                    &provider,
+                )
+            });
+            match result {
+                Ok(app_config) => app_config_to_js(app_config, options.output.unwrap_or_default()),
+                Err(err) => Err(utils::report_error(pipeline::handle_pubgrub_error(err))),
+            }
+        },
+    )
+}
+
+/// Solve the dependencies of a new application, like `elm init` does.
+///
+/// Return the `elm.json` of the new application, for the given elm version,
+/// with the starter packages as direct dependencies,
+/// at their latest compatible versions (or the ones prefered by `js_list_available_versions`).
+/// Package versions whose `elm-version` range does not contain the elm version are never picked,
+/// overriding the `elmVersion` option.
+/// `starter_packages` is an array of package names, like `["elm/http", "mdgriffith/elm-ui"]`,
+/// and defaults to the packages of `elm init`: `elm/browser`, `elm/core` and `elm/html`.
+/// `elm/core` is always added.
+///
+/// When the starter packages cannot be solved together,
+/// the error reports each package conflicting with the ones before it in the list.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn solve_new_application(
+    elm_version: &str,
+    // starter_packages: Vec<String>, can be omitted
+    starter_packages: JsValue,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |mut context| {
+            let elm_version = SemVer::from_str(elm_version)
+                .context("Failed to decode the elm version")
+                .map_err(utils::report_error)?;
+            // Only pick package versions compatible with the elm version of the application.
+            context.options.elm_version = Some(elm_version);
+            let starter_packages: Vec<String> =
+                if starter_packages.is_undefined() || starter_packages.is_null() {
+                    solver::ELM_INIT_PACKAGES
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect()
+                } else {
+                    serde_wasm_bindgen::from_value(starter_packages)?
+                };
+            let starter_packages = (starter_packages.iter())
+                .map(|p| Pkg::from_str(p))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to decode the starter packages")
+                .map_err(utils::report_error)?;
+            let options = &context.options;
+            let provider = context.provider();
+            let result = pipeline::tracked_solve(&provider, || {
+                solver::solve_new_application_js(elm_version, &starter_packages, &provider)
+            });
+            match result {
+                Ok(app_config) => app_config_to_js(app_config, options.output.unwrap_or_default()),
+                Err(solver::NewApplicationError::Solve(err)) => {
+                    Err(utils::report_error(pipeline::handle_pubgrub_error(err)))
+                }
+                Err(solver::NewApplicationError::Conflicts(conflicts)) => {
+                    let reports: Vec<String> = (conflicts.into_iter())
+                        .map(|(pkg, err)| {
+                            format!(
+                                "{} cannot be added:\n\n{:#}",
+                                pkg,
+                                pipeline::handle_pubgrub_error(err)
+                            )
+                        })
+                        .collect();
+                    Err(utils::report_error(anyhow::anyhow!(
+                        "Some starter packages conflict with the ones before them.\n\n{}",
+                        reports.join("\n\n")
+                    )))
+                }
+            }
+        },
+    )
+}
+
+/// Solve the dependencies of an elm-review configuration merged with the ones of a project.
+///
+/// The review configuration is an application `elm.json`, and the project `elm.json`
+/// is either an application, whose direct dependencies are added with their exact versions,
+/// or a package, whose dependencies are added with their ranges.
+/// On failure, the error tells if the conflict comes from the review configuration,
+/// from the project, or from merging them.
+///
+/// Other arguments are the same than for `solve_deps`.
+#[wasm_bindgen]
+pub fn solve_review_config(
+    review_elm_json_str: &str,
+    project_elm_json_str: &str,
+    // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+    js_fetch_elm_json: js_sys::Function,
+    // js_list_available_versions(pkg: &str) -> Vec<String>;
+    js_list_available_versions: js_sys::Function,
+    // options: SolveOptions, can be omitted
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    with_solve_context(
+        options,
+        js_fetch_elm_json,
+        js_list_available_versions,
+        |context| {
+            let review_elm_json = parse_project_elm_json(review_elm_json_str)?;
+            let project_elm_json = parse_project_elm_json(project_elm_json_str)?;
+            let provider = context.project_provider(&review_elm_json);
+            let result = pipeline::tracked_solve(&provider, || {
+                solver::solve_merged_js(&review_elm_json, &project_elm_json, &provider)
+            });
+            match result {
+                Ok(solution) => {
+                    let solve = (&review_elm_json, false, &[][..]);
+                    solution_to_js(Ok(solution), &provider, &context.options, solve)
+                }
+                Err((culprit, err)) => {
+                    let err = pipeline::handle_pubgrub_error(err);
+                    let err = match culprit {
+                        None => err,
+                        Some(solver::Culprit::Base) => {
+                            err.context("The review configuration dependencies have no solution")
+                        }
+                        Some(solver::Culprit::Other) => {
+                            err.context("The project dependencies have no solution")
+                        }
+                        Some(solver::Culprit::Merge) => err.context(
+                            "The review configuration dependencies conflict with the project dependencies",
+                        ),
+                    };
+                    Err(utils::report_error(err))
+                }
+            }
+        },
+    )
+}
+
+/// Parse the project elm.json and additional constraints of a solve.
+///
+/// With the `relaxedPackageNames` option, package names without author
+/// are given an empty author.
+fn parse_solve_input(
+    project_elm_json_str: &str,
+    additional_constraints_str: JsValue,
+    options: &options::SolveOptions,
+) -> Result<(ProjectConfig, Vec<(Pkg, Constraint)>), JsValue> {
+    let additional_constraints: HashMap<String, constraints::AdditionalConstraint> =
+        serde_wasm_bindgen::from_value(additional_constraints_str)?;
+    pipeline::read_solve_input(project_elm_json_str, additional_constraints, options)
+        .map_err(utils::report_error)
+}
+
+/// Convert the solver result into the solution, in the output format, or an error.
+///
+/// Extra fields are added to the solution according to the options.
+fn solution_to_js(
+    result: Result<AppDependencies, PubGrubError<Pkg, SemVer>>,
+    provider: &provider::JsProvider,
+    options: &options::SolveOptions,
+    solve: SolveInput,
+) -> Result<JsValue, JsValue> {
+    let solution = match result {
+        Err(PubGrubError::NoSolution(tree)) if options.error_tree.unwrap_or(false) => {
+            if let Some(err) = pipeline::pins_error(&tree, provider.pins(), options) {
+                return Err(utils::report_error(err));
+            }
+            let hints = pipeline::version_hints(&tree, provider, options, solve);
+            return Err(report::no_solution_error(&tree, &hints, options));
+        }
+        result => result.map_err(|err| {
+            utils::report_error(pipeline::solve_error(err, provider, options, solve))
+        })?,
+    };
+    let solution_json = pipeline::solution_with_fields(&solution, provider, options, solve)
+        .map_err(utils::report_error)?;
+    options.output.unwrap_or_default().to_js(&solution_json)
+}
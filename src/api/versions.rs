@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Versions and constraints, with the same semantics than the solver.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+
+use crate::{constraints, utils};
+
+/// Keep the versions satisfying a constraint, with exactly the semantics of the solver.
+///
+/// The constraint has the syntax of additional constraints, like `"1.0.0 <= v < 2.0.0"`
+/// or `"1.0.0 <= v < 2.0.0 && != 1.3.0"`, and `versions` is an array of version strings.
+/// Return the array of the versions satisfying the constraint, in their original order.
+#[wasm_bindgen]
+pub fn filter_versions(constraint: &str, versions: JsValue) -> Result<JsValue, JsValue> {
+    let constraint = constraints::parse(constraint)
+        .context("Failed to decode the constraint")
+        .map_err(utils::report_error)?;
+    let versions: Vec<String> = serde_wasm_bindgen::from_value(versions)?;
+    let mut kept = Vec::new();
+    for str_version in versions {
+        let version = SemVer::from_str(&str_version)
+            .with_context(|| format!("Invalid version {}", str_version))
+            .map_err(utils::report_error)?;
+        if constraint.0.contains(&version) {
+            kept.push(str_version);
+        }
+    }
+    Ok(serde_wasm_bindgen::to_value(&kept)?)
+}
+
+/// Constraint from a version up to its next major version,
+/// like `until_next_major("2.3.1")` giving `"2.3.1 <= v < 3.0.0"`.
+///
+/// This is how `elm install` and elm-json write the constraints of packages.
+#[wasm_bindgen]
+pub fn until_next_major(version: &str) -> Result<String, JsValue> {
+    let version = parse_version(version)?;
+    Ok(format!("{} <= v < {}", version, version.bump_major()))
+}
+
+/// Constraint from a version up to its next minor version,
+/// like `until_next_minor("2.3.1")` giving `"2.3.1 <= v < 2.4.0"`.
+#[wasm_bindgen]
+pub fn until_next_minor(version: &str) -> Result<String, JsValue> {
+    let version = parse_version(version)?;
+    Ok(format!("{} <= v < {}", version, version.bump_minor()))
+}
+
+/// Order of the versions returned by `sort_versions`.
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum VersionOrder {
+    #[default]
+    LowestFirst,
+    HighestFirst,
+}
+
+/// Sort versions with the same ordering than the solver.
+///
+/// The `order` is either `"lowestFirst"` (the default) or `"highestFirst"`.
+/// The sort is stable, so duplicate versions keep their original order.
+/// Return the array of the original version strings, sorted.
+#[wasm_bindgen]
+pub fn sort_versions(versions: JsValue, order: JsValue) -> Result<JsValue, JsValue> {
+    let versions: Vec<String> = serde_wasm_bindgen::from_value(versions)?;
+    let order: VersionOrder = if order.is_undefined() || order.is_null() {
+        VersionOrder::default()
+    } else {
+        serde_wasm_bindgen::from_value(order)?
+    };
+    let mut parsed = (versions.into_iter())
+        .map(|v| Ok((parse_version(&v)?, v)))
+        .collect::<Result<Vec<_>, JsValue>>()?;
+    match order {
+        VersionOrder::LowestFirst => parsed.sort_by_key(|(v, _)| *v),
+        VersionOrder::HighestFirst => parsed.sort_by_key(|(v, _)| std::cmp::Reverse(*v)),
+    }
+    let sorted: Vec<String> = parsed.into_iter().map(|(_, v)| v).collect();
+    Ok(serde_wasm_bindgen::to_value(&sorted)?)
+}
+
+/// Parse a version given as argument.
+fn parse_version(version: &str) -> Result<SemVer, JsValue> {
+    SemVer::from_str(version)
+        .with_context(|| format!("Invalid version {}", version))
+        .map_err(utils::report_error)
+}
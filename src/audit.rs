@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Audit of the dependencies of a project, combining several checks into one report.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+use elm_solve_deps::project_config::{AppDependencies, ApplicationConfig, Pkg, ProjectConfig};
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::lint;
+use crate::provider::JsProvider;
+
+/// How serious a problem is.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The project is broken, or will be rejected by the elm compiler.
+    Error,
+    /// The project works but could be cleaned up.
+    Warning,
+    /// Nothing to fix, like newer versions being available.
+    Info,
+}
+
+/// A problem found by the audit.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditItem {
+    severity: Severity,
+    /// Name of the check that found the problem.
+    check: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    message: String,
+}
+
+/// Report of the audit of a project.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    /// True if there is no error.
+    passed: bool,
+    errors: usize,
+    warnings: usize,
+    items: Vec<AuditItem>,
+}
+
+/// Audit the dependencies of a project.
+///
+/// The checks are:
+///  - `layout`: packages listed in several sections of the elm.json (error).
+///  - `lockfile`: for applications, versions recorded in the elm.json that do not satisfy
+///    the constraints of the recorded packages, or that are missing (error).
+///  - `minimality`: for applications, indirect dependencies that no direct dependency
+///    needs (warning).
+///  - `outdated`: newer versions of the recorded packages for applications,
+///    or not allowed by the constraints of packages (info).
+pub fn audit(
+    project_elm_json: &ProjectConfig,
+    provider: &JsProvider,
+) -> Result<AuditReport, Box<dyn Error>> {
+    // Consider all versions to find the latest ones.
+    provider.remove_versions_cap();
+    let mut items = Vec::new();
+    for finding in lint::duplicates(project_elm_json) {
+        items.push(AuditItem {
+            severity: Severity::Error,
+            check: "layout",
+            package: finding.package,
+            message: finding.message,
+        });
+    }
+    match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            let recorded = recorded_versions(app_config);
+            lockfile(project_elm_json, &recorded, provider, &mut items);
+            minimality(&recorded, provider, &mut items);
+            let versions = (recorded.direct.iter()).chain(recorded.indirect.iter());
+            for (pkg, current) in versions {
+                let latest = provider.list_available_versions(pkg)?.max();
+                if let Some(latest) = latest.filter(|latest| latest > current) {
+                    items.push(AuditItem {
+                        severity: Severity::Info,
+                        check: "outdated",
+                        package: Some(pkg.to_string()),
+                        message: format!(
+                            "{} {} is outdated, {} is available",
+                            pkg, current, latest
+                        ),
+                    });
+                }
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            let constraints =
+                (pkg_config.dependencies.iter()).chain(pkg_config.test_dependencies.iter());
+            for (pkg, constraint) in constraints {
+                let latest = provider.list_available_versions(pkg)?.max();
+                if let Some(latest) = latest.filter(|latest| !constraint.0.contains(latest)) {
+                    items.push(AuditItem {
+                        severity: Severity::Info,
+                        check: "outdated",
+                        package: Some(pkg.to_string()),
+                        message: format!(
+                            "The constraint {} on {} does not allow its latest version {}",
+                            constraint.0, pkg, latest
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    let count = |severity| items.iter().filter(|i| i.severity == severity).count();
+    let errors = count(Severity::Error);
+    let warnings = count(Severity::Warning);
+    Ok(AuditReport {
+        passed: errors == 0,
+        errors,
+        warnings,
+        items,
+    })
+}
+
+/// All the versions recorded in an application elm.json, test dependencies included.
+fn recorded_versions(app_config: &ApplicationConfig) -> AppDependencies {
+    let deps = &app_config.dependencies;
+    let test_deps = &app_config.test_dependencies;
+    AppDependencies {
+        direct: (deps.direct.iter())
+            .chain(test_deps.direct.iter())
+            .map(|(p, v)| (p.clone(), *v))
+            .collect(),
+        indirect: (deps.indirect.iter())
+            .chain(test_deps.indirect.iter())
+            .map(|(p, v)| (p.clone(), *v))
+            .collect(),
+    }
+}
+
+/// Check that the recorded versions satisfy all the constraints.
+fn lockfile(
+    project_elm_json: &ProjectConfig,
+    recorded: &AppDependencies,
+    provider: &JsProvider,
+    items: &mut Vec<AuditItem>,
+) {
+    let violations = lint::solution_violations(project_elm_json, recorded, |pkg, version| {
+        provider
+            .fetch_elm_json(pkg, version)
+            .map_err(|err| err.to_string())
+    });
+    for violation in violations {
+        items.push(AuditItem {
+            severity: Severity::Error,
+            check: "lockfile",
+            package: Some(violation.package.to_string()),
+            message: violation.message,
+        });
+    }
+}
+
+/// Find the indirect dependencies not needed by any direct dependency.
+fn minimality(recorded: &AppDependencies, provider: &JsProvider, items: &mut Vec<AuditItem>) {
+    let versions: BTreeMap<&Pkg, SemVer> = (recorded.direct.iter())
+        .chain(recorded.indirect.iter())
+        .map(|(p, v)| (p, *v))
+        .collect();
+    let mut reached: BTreeSet<&Pkg> = recorded.direct.keys().collect();
+    let mut to_visit: Vec<&Pkg> = reached.iter().cloned().collect();
+    while let Some(pkg) = to_visit.pop() {
+        // Missing elm.json files are already reported by the lockfile check.
+        let pkg_config = match provider.fetch_elm_json(pkg, versions[pkg]) {
+            Ok(pkg_config) => pkg_config,
+            Err(_) => continue,
+        };
+        for dep in pkg_config.dependencies.keys() {
+            if let Some((dep, _)) = versions.get_key_value(dep) {
+                if reached.insert(dep) {
+                    to_visit.push(dep);
+                }
+            }
+        }
+    }
+    for pkg in recorded.indirect.keys() {
+        if !reached.contains(pkg) {
+            items.push(AuditItem {
+                severity: Severity::Warning,
+                check: "minimality",
+                package: Some(pkg.to_string()),
+                message: format!("{} is not needed by any direct dependency", pkg),
+            });
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Benchmarks of the solver on synthetic registries, to detect performance regressions,
+//! and generation of random registries to stress the solver.
+//!
+//! Benchmark registries are built in memory, so the JS callbacks are not part of the measures.
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{
+    AppDependencies, ApplicationConfig, ExposedModules, PackageConfig, Pkg, ProjectConfig,
+};
+use pubgrub::range::Range;
+use pubgrub::solver::OfflineDependencyProvider;
+use pubgrub::type_aliases::Map;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+
+use crate::{solver, stats};
+
+/// Names of the available scenarios.
+pub const SCENARIOS: [&str; 3] = ["deep-chain", "wide-fanout", "backtracking"];
+
+/// Timing results of a scenario.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchResult {
+    scenario: &'static str,
+    iterations: u32,
+    /// Number of packages in the solution.
+    packages: usize,
+    total_ms: f64,
+    mean_ms: f64,
+    min_ms: f64,
+    max_ms: f64,
+}
+
+/// Run a scenario the given number of times.
+pub fn run(scenario: &str, iterations: u32) -> anyhow::Result<BenchResult> {
+    let (scenario, (registry, direct_deps)) = match scenario {
+        "deep-chain" => (SCENARIOS[0], deep_chain(200, 5)),
+        "wide-fanout" => (SCENARIOS[1], wide_fanout(300, 10)),
+        "backtracking" => (SCENARIOS[2], backtracking(30)),
+        _ => anyhow::bail!(
+            "Unknown benchmark scenario {}, expected one of {:?}",
+            scenario,
+            SCENARIOS
+        ),
+    };
+    let project = ProjectConfig::Application(ApplicationConfig {
+        source_directories: vec!["src".to_string()],
+        elm_version: SemVer::new(0, 19, 1),
+        dependencies: AppDependencies {
+            direct: BTreeMap::new(),
+            indirect: BTreeMap::new(),
+        },
+        test_dependencies: AppDependencies {
+            direct: BTreeMap::new(),
+            indirect: BTreeMap::new(),
+        },
+    });
+    let mut times = Vec::new();
+    let mut packages = 0;
+    for _ in 0..iterations.max(1) {
+        let start = stats::now_ms();
+        let solution = solver::solve_direct_deps(&project, direct_deps.clone(), &registry)
+            .map_err(crate::pipeline::handle_pubgrub_error)?;
+        times.push(stats::now_ms() - start);
+        packages = solution.direct.len() + solution.indirect.len();
+    }
+    let total_ms: f64 = times.iter().sum();
+    Ok(BenchResult {
+        scenario,
+        iterations: times.len() as u32,
+        packages,
+        total_ms,
+        mean_ms: total_ms / times.len() as f64,
+        min_ms: times.iter().cloned().fold(f64::INFINITY, f64::min),
+        max_ms: times.iter().cloned().fold(0.0, f64::max),
+    })
+}
+
+type Scenario = (
+    OfflineDependencyProvider<Pkg, SemVer>,
+    Map<Pkg, Range<SemVer>>,
+);
+
+/// Versions `1.0.0` to `1.(count-1).0`.
+fn minor_versions(count: u32) -> impl Iterator<Item = SemVer> {
+    (0..count).map(|minor| SemVer::new(1, minor, 0))
+}
+
+fn major_range() -> Range<SemVer> {
+    Range::between((1, 0, 0), (2, 0, 0))
+}
+
+/// A chain of packages, each depending on the next one.
+fn deep_chain(length: u32, versions: u32) -> Scenario {
+    let mut registry = OfflineDependencyProvider::new();
+    for i in 0..length {
+        let pkg = Pkg::new("chain", format!("p{}", i));
+        for version in minor_versions(versions) {
+            let deps =
+                (i + 1 < length).then(|| (Pkg::new("chain", format!("p{}", i + 1)), major_range()));
+            registry.add_dependencies(pkg.clone(), version, deps);
+        }
+    }
+    let direct_deps = std::iter::once((Pkg::new("chain", "p0"), major_range())).collect();
+    (registry, direct_deps)
+}
+
+/// Many direct dependencies, all depending on the same core package.
+fn wide_fanout(width: u32, versions: u32) -> Scenario {
+    let mut registry = OfflineDependencyProvider::new();
+    let core = Pkg::new("fanout", "core");
+    for version in minor_versions(versions) {
+        registry.add_dependencies(core.clone(), version, []);
+    }
+    let mut direct_deps = Map::default();
+    for i in 0..width {
+        let pkg = Pkg::new("fanout", format!("p{}", i));
+        for version in minor_versions(versions) {
+            registry.add_dependencies(pkg.clone(), version, [(core.clone(), major_range())]);
+        }
+        direct_deps.insert(pkg, major_range());
+    }
+    (registry, direct_deps)
+}
+
+/// A chain of packages agreeing on a shared package only at its oldest version,
+/// which is only discovered at the end of the chain.
+///
+/// Version `1.k.0` of the package `i` of the chain requires the version `(k + i) % 10`
+/// of the shared package, and the tail of the chain requires its version `1.0.0`,
+/// so the solver walks the whole chain for each version of the shared package.
+fn backtracking(length: u32) -> Scenario {
+    let mut registry = OfflineDependencyProvider::new();
+    let shared = Pkg::new("backtrack", "shared");
+    for version in minor_versions(10) {
+        registry.add_dependencies(shared.clone(), version, []);
+    }
+    let exact_shared = |minor: u32| (shared.clone(), Range::exact((1, minor, 0)));
+    let tail = Pkg::new("backtrack", "tail");
+    registry.add_dependencies(tail.clone(), (1, 0, 0), [exact_shared(0)]);
+    for i in 0..length {
+        let pkg = Pkg::new("backtrack", format!("p{}", i));
+        let next = if i + 1 < length {
+            Pkg::new("backtrack", format!("p{}", i + 1))
+        } else {
+            tail.clone()
+        };
+        for k in 0..10 {
+            let deps = [exact_shared((k + i) % 10), (next.clone(), major_range())];
+            registry.add_dependencies(pkg.clone(), (1, k, 0), deps);
+        }
+    }
+    let direct_deps = std::iter::once((Pkg::new("backtrack", "p0"), major_range())).collect();
+    (registry, direct_deps)
+}
+
+/// Parameters of a generated registry, all optional.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RegistryParams {
+    /// Seed of the random generator, the same seed gives the same registry, 0 by default.
+    pub seed: Option<u64>,
+    /// Number of packages, 100 by default.
+    pub packages: Option<u32>,
+    /// Number of versions of each package, 10 by default.
+    pub versions_per_package: Option<u32>,
+    /// Maximum number of dependencies of each package version, 3 by default.
+    pub dependencies_per_version: Option<u32>,
+    /// Number of dependencies of the generated project, 5 by default.
+    pub direct_dependencies: Option<u32>,
+    /// Probability between 0 and 1 that a constraint only allows one minor version,
+    /// instead of all the versions up to the next major, 0.2 by default.
+    pub tightness: Option<f64>,
+}
+
+/// Randomly generated registry, with a project depending on it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedRegistry {
+    /// JSON string of the elm.json of a package depending on registry packages.
+    elm_json: String,
+    /// Available versions of each package, newest first.
+    versions: BTreeMap<Pkg, Vec<SemVer>>,
+    /// JSON string of the elm.json of each package version.
+    packages: BTreeMap<Pkg, BTreeMap<SemVer, String>>,
+}
+
+/// Generate a random registry.
+///
+/// Packages only depend on packages with a higher index,
+/// like the registry, where dependency cycles are impossible.
+pub fn generate_registry(params: &RegistryParams) -> GeneratedRegistry {
+    let package_count = params.packages.unwrap_or(100);
+    let tightness = params.tightness.unwrap_or(0.2);
+    let mut rng = SplitMix64(params.seed.unwrap_or(0));
+    let pkg = |i: u32| Pkg::new("synthetic", format!("p{}", i));
+    let versions: Vec<SemVer> = (0..params.versions_per_package.unwrap_or(10).max(1))
+        .map(|j| SemVer::new(1 + j / 4, j % 4, 0))
+        .collect();
+    let random_constraint = |rng: &mut SplitMix64| {
+        let low = versions[rng.below(versions.len() as u64) as usize];
+        let high = if rng.unit() < tightness {
+            low.bump_minor()
+        } else {
+            low.bump_major()
+        };
+        Constraint(Range::between(low, high))
+    };
+    let random_dependencies = |rng: &mut SplitMix64, from: u32, count: u32| {
+        let mut deps = BTreeMap::new();
+        for _ in 0..count {
+            if from < package_count {
+                let dep = from + rng.below((package_count - from) as u64) as u32;
+                deps.insert(pkg(dep), random_constraint(rng));
+            }
+        }
+        deps
+    };
+
+    let mut packages = BTreeMap::new();
+    for i in 0..package_count {
+        let mut pkg_versions = BTreeMap::new();
+        for version in &versions {
+            let count = rng.below(params.dependencies_per_version.unwrap_or(3) as u64 + 1) as u32;
+            let config = synthetic_package(
+                pkg(i),
+                *version,
+                random_dependencies(&mut rng, i + 1, count),
+            );
+            pkg_versions.insert(*version, serde_json::to_string(&config).unwrap());
+        }
+        packages.insert(pkg(i), pkg_versions);
+    }
+    let project_deps = random_dependencies(&mut rng, 0, params.direct_dependencies.unwrap_or(5));
+    let project = synthetic_package(
+        Pkg::new("synthetic", "root"),
+        SemVer::new(1, 0, 0),
+        project_deps,
+    );
+    let newest_first: Vec<SemVer> = versions.iter().rev().cloned().collect();
+    GeneratedRegistry {
+        elm_json: serde_json::to_string(&project).unwrap(),
+        versions: (packages.keys().map(|p| (p.clone(), newest_first.clone()))).collect(),
+        packages,
+    }
+}
+
+fn synthetic_package(
+    name: Pkg,
+    version: SemVer,
+    dependencies: BTreeMap<Pkg, Constraint>,
+) -> ProjectConfig {
+    ProjectConfig::Package(PackageConfig {
+        name,
+        summary: "Synthetic package".to_string(),
+        license: "BSD-3-Clause".to_string(),
+        version,
+        elm_version: Constraint(Range::between((0, 19, 0), (0, 20, 0))),
+        exposed_modules: ExposedModules::NoCategory(vec!["Main".to_string()]),
+        dependencies,
+        test_dependencies: BTreeMap::new(),
+    })
+}
+
+/// Small seedable random generator (SplitMix64), enough for synthetic registries.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `0..n`, or 0 if `n` is 0.
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next() % n
+        }
+    }
+
+    /// Random number in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
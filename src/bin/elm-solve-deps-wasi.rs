@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Solver executable speaking the line-based JSON protocol of the `wasi` module
+//! over stdin and stdout.
+
+fn main() {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(err) = elm_solve_deps_wasm::wasi::run(stdin.lock(), stdout.lock()) {
+        eprintln!("{:?}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Parsing and writing of additional constraints, extending the syntax of elm.json constraints.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::constraint::Constraint;
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+
+/// Additional constraint on a package, as provided by the caller.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AdditionalConstraint {
+    /// A single constraint.
+    Single(String),
+    /// Several constraints, whose ranges are unioned.
+    Union(Vec<String>),
+}
+
+impl AdditionalConstraint {
+    /// Parse the constraint, unioning the ranges of a list of constraints.
+    pub fn parse(&self) -> anyhow::Result<Constraint> {
+        match self {
+            Self::Single(constraint) => parse(constraint),
+            Self::Union(constraints) => {
+                let mut range = Range::none();
+                for constraint in constraints {
+                    range = range.union(&parse(constraint)?.0);
+                }
+                Ok(Constraint(range))
+            }
+        }
+    }
+}
+
+/// Parse an additional constraint.
+///
+/// On top of the elm.json syntax, like `1.0.0 <= v < 2.0.0`,
+/// a constraint can exclude a version with `!= 1.3.0`,
+/// and several constraints can be combined with `&&`,
+/// like `1.0.0 <= v < 2.0.0 && != 1.3.0`.
+pub fn parse(constraint: &str) -> anyhow::Result<Constraint> {
+    let mut range = Range::any();
+    for part in constraint.split("&&").map(str::trim) {
+        let part_range = match part.strip_prefix("!=") {
+            Some(version) => {
+                let version = SemVer::from_str(version.trim())
+                    .with_context(|| format!("Invalid excluded version in \"{}\"", constraint))?;
+                Range::exact(version).negate()
+            }
+            None => Constraint::from_str(part)?.0,
+        };
+        range = range.intersection(&part_range);
+    }
+    Ok(Constraint(range))
+}
+
+/// Upper bound written for ranges without one, since the syntax requires an upper bound.
+const HIGHEST_MAJOR: u32 = u32::MAX;
+
+/// Write a range with the syntax of additional constraints, the inverse of `parse`.
+///
+/// A range with a single interval is written like in elm.json, like `1.0.0 <= v < 2.0.0`,
+/// and versions excluded from a single interval are appended, like `&& != 1.3.0`.
+/// Other ranges are written as the list of their intervals, whose union is the range.
+/// Intervals without upper bound end before the version `4294967295.0.0`.
+pub fn format(range: &Range<SemVer>) -> AdditionalConstraint {
+    let intervals = intervals(range);
+    let holes: Option<Vec<SemVer>> = (intervals.windows(2))
+        .map(|pair| pair[0].1.filter(|end| end.bump_patch() == pair[1].0))
+        .collect();
+    match (intervals.first(), intervals.last(), holes) {
+        (Some((start, _)), Some((_, end)), Some(holes)) => {
+            let mut constraint = format_interval(*start, *end);
+            for hole in holes {
+                constraint.push_str(&format!(" && != {}", hole));
+            }
+            AdditionalConstraint::Single(constraint)
+        }
+        _ => AdditionalConstraint::Union(
+            (intervals.into_iter())
+                .map(|(start, end)| format_interval(start, end))
+                .collect(),
+        ),
+    }
+}
+
+/// Write an interval like in elm.json, `start <= v < end`.
+fn format_interval(start: SemVer, end: Option<SemVer>) -> String {
+    let end = end.unwrap_or_else(|| SemVer::new(HIGHEST_MAJOR, 0, 0));
+    format!("{} <= v < {}", start, end)
+}
+
+/// Intervals `[start, end[` of a range, in increasing order.
+fn intervals(range: &Range<SemVer>) -> Vec<(SemVer, Option<SemVer>)> {
+    // They are private, but serialized as the list of `(start, end)` pairs.
+    serde_json::from_value(serde_json::to_value(range).unwrap()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> SemVer {
+        SemVer::from_str(version).unwrap()
+    }
+
+    fn single(constraint: &str) -> AdditionalConstraint {
+        AdditionalConstraint::Single(constraint.to_string())
+    }
+
+    #[test]
+    fn parse_elm_json_constraint() {
+        let range = parse("1.0.0 <= v < 2.0.0").unwrap().0;
+        assert_eq!(range, Range::between(v("1.0.0"), v("2.0.0")));
+    }
+
+    #[test]
+    fn parse_intersection() {
+        let range = parse("1.0.0 <= v < 3.0.0 && 2.0.0 <= v < 4.0.0").unwrap().0;
+        assert_eq!(range, Range::between(v("2.0.0"), v("3.0.0")));
+    }
+
+    #[test]
+    fn parse_excluded_version() {
+        let range = parse("1.0.0 <= v < 2.0.0 && != 1.3.0").unwrap().0;
+        assert!(range.contains(&v("1.2.9")));
+        assert!(!range.contains(&v("1.3.0")));
+        assert!(range.contains(&v("1.3.1")));
+        assert!(!range.contains(&v("2.0.0")));
+    }
+
+    #[test]
+    fn parse_several_excluded_versions() {
+        let range = parse("!= 1.0.0 && 1.0.0 <= v < 2.0.0 && != 1.0.1")
+            .unwrap()
+            .0;
+        assert!(!range.contains(&v("1.0.0")));
+        assert!(!range.contains(&v("1.0.1")));
+        assert!(range.contains(&v("1.0.2")));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse("1.0.0 <= v < 2.0.0 && != 1.x").is_err());
+        assert!(parse("1.0.0 <= v < 2.0.0 &&").is_err());
+        assert!(parse("1.0.0").is_err());
+    }
+
+    #[test]
+    fn parse_array_as_union() {
+        let constraint: AdditionalConstraint =
+            serde_json::from_str(r#"["1.0.0 <= v < 2.0.0", "3.0.0 <= v < 4.0.0 && != 3.1.0"]"#)
+                .unwrap();
+        let range = constraint.parse().unwrap().0;
+        assert!(range.contains(&v("1.5.0")));
+        assert!(!range.contains(&v("2.5.0")));
+        assert!(range.contains(&v("3.0.0")));
+        assert!(!range.contains(&v("3.1.0")));
+        assert!(range.contains(&v("3.1.1")));
+    }
+
+    #[test]
+    fn parse_empty_array() {
+        let constraint = AdditionalConstraint::Union(Vec::new());
+        assert_eq!(constraint.parse().unwrap().0, Range::none());
+    }
+
+    #[test]
+    fn format_interval() {
+        let range = Range::between(v("1.0.0"), v("2.0.0"));
+        assert_eq!(format(&range), single("1.0.0 <= v < 2.0.0"));
+    }
+
+    #[test]
+    fn format_from_lowest_version() {
+        let range = Range::strictly_lower_than(v("1.0.0"));
+        assert_eq!(format(&range), single("0.0.0 <= v < 1.0.0"));
+    }
+
+    #[test]
+    fn format_exact_version() {
+        let range = Range::exact(v("1.2.3"));
+        assert_eq!(format(&range), single("1.2.3 <= v < 1.2.4"));
+    }
+
+    #[test]
+    fn format_without_upper_bound() {
+        let range = Range::higher_than(v("1.0.0"));
+        assert_eq!(format(&range), single("1.0.0 <= v < 4294967295.0.0"));
+    }
+
+    #[test]
+    fn format_excluded_versions() {
+        let constraint = "1.0.0 <= v < 2.0.0 && != 1.3.0 && != 1.5.2";
+        assert_eq!(format(&parse(constraint).unwrap().0), single(constraint));
+    }
+
+    #[test]
+    fn format_union() {
+        let range =
+            Range::between(v("1.0.0"), v("2.0.0")).union(&Range::between(v("3.0.0"), v("4.0.0")));
+        assert_eq!(
+            format(&range),
+            AdditionalConstraint::Union(vec![
+                "1.0.0 <= v < 2.0.0".to_string(),
+                "3.0.0 <= v < 4.0.0".to_string(),
+            ])
+        );
+    }
+
+    /// Ranges without upper bound are not included, since one is written for them.
+    #[test]
+    fn format_round_trips() {
+        let ranges = [
+            Range::between(v("1.0.0"), v("2.0.0")),
+            Range::exact(v("0.1.0")),
+            Range::strictly_lower_than(v("2.0.0")),
+            parse("1.0.0 <= v < 2.0.0 && != 1.3.0").unwrap().0,
+            Range::exact(v("1.0.0")).union(&Range::between(v("3.0.0"), v("4.0.0"))),
+            Range::none(),
+        ];
+        for range in ranges {
+            assert_eq!(format(&range).parse().unwrap().0, range);
+        }
+    }
+}
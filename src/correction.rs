@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Minimal set of project constraints to remove to make the resolution possible.
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::range::Range;
+use pubgrub::solver::DependencyProvider;
+use pubgrub::type_aliases::Map;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::solver;
+
+/// Constraint of the project on one of its dependencies.
+#[derive(Serialize)]
+pub struct ProjectConstraint {
+    package: Pkg,
+    constraint: String,
+    /// Where the constraint comes from:
+    /// `"dependencies"`, `"test-dependencies"` or `"additional"`.
+    source: &'static str,
+    #[serde(skip)]
+    range: Range<SemVer>,
+}
+
+/// Find a minimal set of project constraints whose removal makes the resolution possible.
+///
+/// Constraints are added one at a time, in the order of the elm.json,
+/// and those making the resolution impossible with the ones already kept are removed.
+/// No removed constraint can then be added back without making the resolution impossible.
+/// The set is empty if the resolution is already possible.
+pub fn minimal_correction_set<DP: DependencyProvider<Pkg, SemVer>>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    provider: &DP,
+) -> Result<Vec<ProjectConstraint>, PubGrubError<Pkg, SemVer>> {
+    let constraints = project_constraints(project_elm_json, use_test, additional_constraints);
+    let solvable = |kept: &[&ProjectConstraint]| {
+        let mut direct_deps: Map<Pkg, Range<SemVer>> = Map::default();
+        for c in kept {
+            let range = direct_deps
+                .entry(c.package.clone())
+                .or_insert_with(Range::any);
+            *range = range.intersection(&c.range);
+        }
+        match solver::solve_direct_deps(project_elm_json, direct_deps, provider) {
+            Ok(_) => Ok(true),
+            Err(PubGrubError::NoSolution(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    };
+    let all: Vec<&ProjectConstraint> = constraints.iter().collect();
+    if solvable(&all)? {
+        return Ok(Vec::new());
+    }
+    let mut kept = Vec::new();
+    let mut removed = Vec::new();
+    for (i, constraint) in constraints.iter().enumerate() {
+        kept.push(constraint);
+        if !solvable(&kept)? {
+            kept.pop();
+            removed.push(i);
+        }
+    }
+    Ok((constraints.into_iter().enumerate())
+        .filter(|(i, _)| removed.contains(i))
+        .map(|(_, c)| c)
+        .collect())
+}
+
+/// All the constraints of the project, in the order of the elm.json,
+/// followed by the additional constraints.
+fn project_constraints(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+) -> Vec<ProjectConstraint> {
+    let mut constraints = Vec::new();
+    let mut push = |package: &Pkg, range: Range<SemVer>, source| {
+        constraints.push(ProjectConstraint {
+            package: package.clone(),
+            constraint: range.to_string(),
+            source,
+            range,
+        })
+    };
+    match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            for (p, v) in &app_config.dependencies.direct {
+                push(p, Range::exact(*v), "dependencies");
+            }
+            if use_test {
+                for (p, v) in &app_config.test_dependencies.direct {
+                    push(p, Range::exact(*v), "test-dependencies");
+                }
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            for (p, c) in &pkg_config.dependencies {
+                push(p, c.0.clone(), "dependencies");
+            }
+            if use_test {
+                for (p, c) in &pkg_config.test_dependencies {
+                    push(p, c.0.clone(), "test-dependencies");
+                }
+            }
+        }
+    }
+    for (p, c) in additional_constraints {
+        push(p, c.0.clone(), "additional");
+    }
+    constraints
+}
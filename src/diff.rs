@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Comparison of the dependencies of two elm.json files.
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use serde::Serialize;
+
+/// Kind of change of a dependency.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Change of a dependency between two elm.json files.
+#[derive(Serialize)]
+pub struct DependencyChange {
+    package: Pkg,
+    /// Section of the dependency, like `"dependencies"` for a package,
+    /// or `"dependencies.direct"` for an application.
+    section: &'static str,
+    kind: ChangeKind,
+    /// Version or constraint before the change, absent if added.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<String>,
+    /// Version or constraint after the change, absent if removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+}
+
+/// Compare the dependency sections of two elm.json files.
+///
+/// A package moving from one section to another is removed from the first one
+/// and added to the other. Changes are sorted by section and package.
+pub fn diff_projects(before: &ProjectConfig, after: &ProjectConfig) -> Vec<DependencyChange> {
+    let before = dependencies(before);
+    let mut after = dependencies(after);
+    let mut changes = Vec::new();
+    for ((section, package), old) in before {
+        let change = match after.remove(&(section, package.clone())) {
+            None => (ChangeKind::Removed, Some(old), None),
+            Some(new) if new != old => (ChangeKind::Changed, Some(old), Some(new)),
+            Some(_) => continue,
+        };
+        changes.push((section, package, change));
+    }
+    for ((section, package), new) in after {
+        changes.push((section, package, (ChangeKind::Added, None, Some(new))));
+    }
+    changes.sort_by(|(s1, p1, _), (s2, p2, _)| (s1, p1).cmp(&(s2, p2)));
+    (changes.into_iter())
+        .map(
+            |(section, package, (kind, before, after))| DependencyChange {
+                package,
+                section,
+                kind,
+                before,
+                after,
+            },
+        )
+        .collect()
+}
+
+/// Version or constraint of every dependency of an elm.json, by section and package.
+fn dependencies(config: &ProjectConfig) -> BTreeMap<(&'static str, Pkg), String> {
+    let mut deps = BTreeMap::new();
+    match config {
+        ProjectConfig::Application(app_config) => {
+            let sections = [
+                ("dependencies.direct", &app_config.dependencies.direct),
+                ("dependencies.indirect", &app_config.dependencies.indirect),
+                (
+                    "test-dependencies.direct",
+                    &app_config.test_dependencies.direct,
+                ),
+                (
+                    "test-dependencies.indirect",
+                    &app_config.test_dependencies.indirect,
+                ),
+            ];
+            for (section, versions) in sections {
+                for (pkg, version) in versions {
+                    deps.insert((section, pkg.clone()), version.to_string());
+                }
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            let sections = [
+                ("dependencies", &pkg_config.dependencies),
+                ("test-dependencies", &pkg_config.test_dependencies),
+            ];
+            for (section, constraints) in sections {
+                for (pkg, constraint) in constraints {
+                    deps.insert((section, pkg.clone()), constraint.0.to_string());
+                }
+            }
+        }
+    }
+    deps
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn application(direct: &str, indirect: &str, test_direct: &str) -> ProjectConfig {
+        let elm_json = format!(
+            r#"{{
+                "type": "application",
+                "source-directories": ["src"],
+                "elm-version": "0.19.1",
+                "dependencies": {{ "direct": {{ {} }}, "indirect": {{ {} }} }},
+                "test-dependencies": {{ "direct": {{ {} }}, "indirect": {{}} }}
+            }}"#,
+            direct, indirect, test_direct
+        );
+        serde_json::from_str(&elm_json).unwrap()
+    }
+
+    fn package(dependencies: &str) -> ProjectConfig {
+        let elm_json = format!(
+            r#"{{
+                "type": "package",
+                "name": "author/pkg",
+                "summary": "",
+                "license": "BSD-3-Clause",
+                "version": "1.0.0",
+                "exposed-modules": [],
+                "elm-version": "0.19.0 <= v < 0.20.0",
+                "dependencies": {{ {} }},
+                "test-dependencies": {{}}
+            }}"#,
+            dependencies
+        );
+        serde_json::from_str(&elm_json).unwrap()
+    }
+
+    #[test]
+    fn no_changes() {
+        let config = application(r#""elm/core": "1.0.5""#, "", "");
+        assert!(diff_projects(&config, &config).is_empty());
+    }
+
+    #[test]
+    fn application_changes() {
+        let before = application(
+            r#""elm/core": "1.0.4", "elm/json": "1.1.3""#,
+            r#""elm/time": "1.0.0""#,
+            "",
+        );
+        let after = application(
+            r#""elm/core": "1.0.5", "elm/time": "1.0.0""#,
+            "",
+            r#""elm-explorations/test": "1.2.2""#,
+        );
+        let changes = serde_json::to_value(diff_projects(&before, &after)).unwrap();
+        assert_eq!(
+            changes,
+            json!([
+                {
+                    "package": "elm/core",
+                    "section": "dependencies.direct",
+                    "kind": "changed",
+                    "before": "1.0.4",
+                    "after": "1.0.5",
+                },
+                {
+                    "package": "elm/json",
+                    "section": "dependencies.direct",
+                    "kind": "removed",
+                    "before": "1.1.3",
+                },
+                {
+                    "package": "elm/time",
+                    "section": "dependencies.direct",
+                    "kind": "added",
+                    "after": "1.0.0",
+                },
+                {
+                    "package": "elm/time",
+                    "section": "dependencies.indirect",
+                    "kind": "removed",
+                    "before": "1.0.0",
+                },
+                {
+                    "package": "elm-explorations/test",
+                    "section": "test-dependencies.direct",
+                    "kind": "added",
+                    "after": "1.2.2",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn package_changes() {
+        let before = package(r#""elm/core": "1.0.0 <= v < 2.0.0""#);
+        let after = package(r#""elm/core": "1.0.2 <= v < 2.0.0""#);
+        let changes = serde_json::to_value(diff_projects(&before, &after)).unwrap();
+        assert_eq!(
+            changes,
+            json!([{
+                "package": "elm/core",
+                "section": "dependencies",
+                "kind": "changed",
+                "before": "1.0.0 <= v < 2.0.0",
+                "after": "1.0.2 <= v < 2.0.0",
+            }])
+        );
+    }
+}
@@ -0,0 +1,434 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Modification of elm.json files, and their serialization back to text.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, ExposedModules, Pkg, ProjectConfig};
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::de::{IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::constraints::{self, AdditionalConstraint};
+
+/// Section of an elm.json where a dependency is added or removed.
+#[derive(Clone, Copy, Default)]
+pub struct Section {
+    /// Whether this is the test dependencies.
+    pub test: bool,
+    /// Whether this is the indirect dependencies (only for applications).
+    pub indirect: bool,
+}
+
+/// Add a dependency to an elm.json, or change its version or constraint.
+///
+/// For an application, `value` is the version of the package,
+/// and the package is removed from the other half (direct or indirect) of the section.
+/// For a package, `value` is a constraint like `1.0.0 <= v < 2.0.0`,
+/// or a version, allowing all versions up to the next major.
+pub fn set_dependency(
+    config: &mut ProjectConfig,
+    pkg: Pkg,
+    value: &str,
+    section: Section,
+) -> anyhow::Result<()> {
+    match config {
+        ProjectConfig::Application(app_config) => {
+            let version = SemVer::from_str(value)
+                .with_context(|| format!("Invalid version {} of {}", value, pkg))?;
+            let deps = app_deps_mut(
+                &mut app_config.dependencies,
+                &mut app_config.test_dependencies,
+                section.test,
+            );
+            if section.indirect {
+                deps.direct.remove(&pkg);
+                deps.indirect.insert(pkg, version);
+            } else {
+                deps.indirect.remove(&pkg);
+                deps.direct.insert(pkg, version);
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            if section.indirect {
+                anyhow::bail!("Packages have no indirect dependencies");
+            }
+            let constraint = match SemVer::from_str(value) {
+                Ok(version) => Constraint(Range::between(version, version.bump_major())),
+                Err(_) => Constraint::from_str(value)
+                    .map_err(|err| anyhow::anyhow!("{}", err))
+                    .with_context(|| format!("Invalid constraint {} of {}", value, pkg))?,
+            };
+            if section.test {
+                pkg_config.test_dependencies.insert(pkg, constraint);
+            } else {
+                pkg_config.dependencies.insert(pkg, constraint);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove a dependency from an elm.json, direct or indirect.
+///
+/// Return whether the package was in that section.
+pub fn remove_dependency(config: &mut ProjectConfig, pkg: &Pkg, test: bool) -> bool {
+    match config {
+        ProjectConfig::Application(app_config) => {
+            let deps = app_deps_mut(
+                &mut app_config.dependencies,
+                &mut app_config.test_dependencies,
+                test,
+            );
+            let direct = deps.direct.remove(pkg).is_some();
+            let indirect = deps.indirect.remove(pkg).is_some();
+            direct || indirect
+        }
+        ProjectConfig::Package(pkg_config) if test => {
+            pkg_config.test_dependencies.remove(pkg).is_some()
+        }
+        ProjectConfig::Package(pkg_config) => pkg_config.dependencies.remove(pkg).is_some(),
+    }
+}
+
+fn app_deps_mut<'a>(
+    dependencies: &'a mut AppDependencies,
+    test_dependencies: &'a mut AppDependencies,
+    test: bool,
+) -> &'a mut AppDependencies {
+    if test {
+        test_dependencies
+    } else {
+        dependencies
+    }
+}
+
+/// Serialize an elm.json the way the elm compiler writes it,
+/// with 4 spaces indentation and a trailing new line.
+pub fn to_elm_json_string(elm_json: &ElmJson) -> String {
+    let mut json = to_pretty_json(elm_json);
+    json.push('\n');
+    json
+}
+
+/// Serialize a value to JSON in the style of the elm compiler, with 4 spaces indentation.
+pub fn to_pretty_json<T: Serialize>(value: &T) -> String {
+    let mut bytes = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, formatter);
+    value.serialize(&mut serializer).unwrap();
+    String::from_utf8(bytes).unwrap()
+}
+
+/// Project config serialized with the fields in the order of the elm compiler.
+///
+/// The serialization of `ProjectConfig` writes `elm-version` before `exposed-modules`,
+/// sorts the categories of exposed modules, and writes constraints like `0.0.0 <= v < 1.0.0`
+/// as `v < 1.0.0`, unlike the compiler.
+pub struct ElmJson<'a> {
+    config: &'a ProjectConfig,
+    categories: &'a [String],
+}
+
+impl<'a> ElmJson<'a> {
+    /// Project config with its categories of exposed modules in alphabetical order.
+    pub fn new(config: &'a ProjectConfig) -> Self {
+        Self {
+            config,
+            categories: &[],
+        }
+    }
+
+    /// Write the categories of exposed modules in the given order,
+    /// the ones not listed being written after, in alphabetical order.
+    pub fn with_categories(mut self, categories: &'a [String]) -> Self {
+        self.categories = categories;
+        self
+    }
+}
+
+impl Serialize for ElmJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self.config {
+            ProjectConfig::Application(app_config) => {
+                map.serialize_entry("type", "application")?;
+                map.serialize_entry("source-directories", &app_config.source_directories)?;
+                map.serialize_entry("elm-version", &app_config.elm_version)?;
+                map.serialize_entry("dependencies", &app_config.dependencies)?;
+                map.serialize_entry("test-dependencies", &app_config.test_dependencies)?;
+            }
+            ProjectConfig::Package(pkg_config) => {
+                let exposed_modules = OrderedModules {
+                    modules: &pkg_config.exposed_modules,
+                    categories: self.categories,
+                };
+                map.serialize_entry("type", "package")?;
+                map.serialize_entry("name", &pkg_config.name)?;
+                map.serialize_entry("summary", &pkg_config.summary)?;
+                map.serialize_entry("license", &pkg_config.license)?;
+                map.serialize_entry("version", &pkg_config.version)?;
+                map.serialize_entry("exposed-modules", &exposed_modules)?;
+                map.serialize_entry(
+                    "elm-version",
+                    &constraints::format(&pkg_config.elm_version.0),
+                )?;
+                map.serialize_entry("dependencies", &formatted(&pkg_config.dependencies))?;
+                map.serialize_entry(
+                    "test-dependencies",
+                    &formatted(&pkg_config.test_dependencies),
+                )?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// Constraints written like in elm.json, which their own serialization does not always do,
+/// like `v < 2.0.0` for `0.0.0 <= v < 2.0.0`.
+fn formatted(dependencies: &BTreeMap<Pkg, Constraint>) -> BTreeMap<&Pkg, AdditionalConstraint> {
+    (dependencies.iter())
+        .map(|(pkg, constraint)| (pkg, constraints::format(&constraint.0)))
+        .collect()
+}
+
+/// Exposed modules with their categories in a given order.
+struct OrderedModules<'a> {
+    modules: &'a ExposedModules,
+    categories: &'a [String],
+}
+
+impl Serialize for OrderedModules<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let by_category = match self.modules {
+            ExposedModules::NoCategory(modules) => return modules.serialize(serializer),
+            ExposedModules::WithCategories(by_category) => by_category,
+        };
+        let mut map = serializer.serialize_map(Some(by_category.len()))?;
+        let listed = (self.categories.iter()).filter(|c| by_category.contains_key(*c));
+        let others = (by_category.keys()).filter(|c| !self.categories.contains(c));
+        for category in listed.chain(others) {
+            map.serialize_entry(category, &by_category[category])?;
+        }
+        map.end()
+    }
+}
+
+/// Categories of the exposed modules of an elm.json, in their original order.
+///
+/// The project config keeps them in a sorted map, so they are read separately.
+#[derive(Default, Deserialize)]
+pub struct ModuleCategories {
+    #[serde(
+        rename = "exposed-modules",
+        default,
+        deserialize_with = "deserialize_keys"
+    )]
+    pub categories: Vec<String>,
+}
+
+impl ModuleCategories {
+    /// Read the categories from the text of an elm.json, none if it has no categories.
+    pub fn read(elm_json_str: &str) -> Self {
+        serde_json::from_str(elm_json_str).unwrap_or_default()
+    }
+}
+
+/// Deserialize the keys of an object in their original order,
+/// or nothing if this is not an object.
+fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    struct KeysVisitor;
+
+    impl<'de> Visitor<'de> for KeysVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("exposed modules")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut keys = Vec::new();
+            while let Some((key, IgnoredAny)) = map.next_entry()? {
+                keys.push(key);
+            }
+            Ok(keys)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Ok(Vec::new())
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+    }
+
+    deserializer.deserialize_any(KeysVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE: &str = r#"{
+    "type": "package",
+    "name": "author/pkg",
+    "summary": "A package",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": {
+        "Zeta": [
+            "Zeta",
+            "Zeta.Internal"
+        ],
+        "Alpha": [
+            "Alpha"
+        ]
+    },
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "elm/json": "0.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}
+"#;
+
+    const APPLICATION: &str = r#"{
+    "type": "application",
+    "source-directories": [
+        "src"
+    ],
+    "elm-version": "0.19.1",
+    "dependencies": {
+        "direct": {
+            "elm/core": "1.0.5"
+        },
+        "indirect": {
+            "elm/json": "1.1.3"
+        }
+    },
+    "test-dependencies": {
+        "direct": {},
+        "indirect": {}
+    }
+}
+"#;
+
+    fn config(elm_json: &str) -> ProjectConfig {
+        serde_json::from_str(elm_json).unwrap()
+    }
+
+    fn pkg(name: &str) -> Pkg {
+        Pkg::from_str(name).unwrap()
+    }
+
+    fn rewrite(config: &ProjectConfig, original: &str) -> String {
+        let categories = ModuleCategories::read(original).categories;
+        to_elm_json_string(&ElmJson::new(config).with_categories(&categories))
+    }
+
+    #[test]
+    fn package_written_like_the_compiler() {
+        assert_eq!(rewrite(&config(PACKAGE), PACKAGE), PACKAGE);
+    }
+
+    #[test]
+    fn application_written_like_the_compiler() {
+        assert_eq!(rewrite(&config(APPLICATION), APPLICATION), APPLICATION);
+    }
+
+    #[test]
+    fn categories_sorted_by_default() {
+        let json = to_elm_json_string(&ElmJson::new(&config(PACKAGE)));
+        let alpha = json.find("\"Alpha\": [").unwrap();
+        let zeta = json.find("\"Zeta\": [").unwrap();
+        assert!(alpha < zeta);
+    }
+
+    #[test]
+    fn read_categories() {
+        assert_eq!(
+            ModuleCategories::read(PACKAGE).categories,
+            ["Zeta", "Alpha"]
+        );
+        let no_category = PACKAGE.replace(
+            r#"{
+        "Zeta": [
+            "Zeta",
+            "Zeta.Internal"
+        ],
+        "Alpha": [
+            "Alpha"
+        ]
+    }"#,
+            r#"["Alpha"]"#,
+        );
+        assert!(ModuleCategories::read(&no_category).categories.is_empty());
+        assert!(ModuleCategories::read(APPLICATION).categories.is_empty());
+    }
+
+    #[test]
+    fn set_package_dependency() {
+        let mut config = config(PACKAGE);
+        set_dependency(&mut config, pkg("elm/http"), "2.0.0", Section::default()).unwrap();
+        let test = Section {
+            test: true,
+            indirect: false,
+        };
+        set_dependency(&mut config, pkg("elm/test"), "1.2.0 <= v < 1.3.0", test).unwrap();
+        let json = rewrite(&config, PACKAGE);
+        assert!(json.contains(r#""elm/http": "2.0.0 <= v < 3.0.0""#));
+        assert!(json.contains(
+            r#""test-dependencies": {
+        "elm/test": "1.2.0 <= v < 1.3.0"
+    }"#
+        ));
+    }
+
+    #[test]
+    fn set_invalid_package_dependency() {
+        let mut config = config(PACKAGE);
+        let section = Section::default();
+        assert!(set_dependency(&mut config, pkg("elm/http"), "2.0", section).is_err());
+        let indirect = Section {
+            test: false,
+            indirect: true,
+        };
+        assert!(set_dependency(&mut config, pkg("elm/http"), "2.0.0", indirect).is_err());
+    }
+
+    #[test]
+    fn set_application_dependency() {
+        let mut config = config(APPLICATION);
+        set_dependency(&mut config, pkg("elm/json"), "1.1.3", Section::default()).unwrap();
+        let json = rewrite(&config, APPLICATION);
+        assert!(json.contains(
+            r#""direct": {
+            "elm/core": "1.0.5",
+            "elm/json": "1.1.3"
+        },
+        "indirect": {}"#
+        ));
+        assert!(set_dependency(&mut config, pkg("elm/json"), "1.1", Section::default()).is_err());
+    }
+
+    #[test]
+    fn remove_dependencies() {
+        let mut config = config(PACKAGE);
+        assert!(remove_dependency(&mut config, &pkg("elm/json"), false));
+        assert!(!remove_dependency(&mut config, &pkg("elm/json"), false));
+        assert!(!remove_dependency(&mut config, &pkg("elm/core"), true));
+        assert!(!rewrite(&config, PACKAGE).contains("elm/json"));
+        let mut config = self::config(APPLICATION);
+        assert!(remove_dependency(&mut config, &pkg("elm/json"), false));
+        assert!(!rewrite(&config, APPLICATION).contains("elm/json"));
+    }
+}
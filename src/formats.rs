@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conversion of solutions into the configurations expected by other tools.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Write;
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, ApplicationConfig, Pkg};
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+
+/// Elm version of the tests application generated by elm-test-rs.
+pub const ELM_TEST_RS_ELM_VERSION: (u32, u32, u32) = (0, 19, 1);
+
+/// Application configuration running tests, with the shape generated by elm-test-rs.
+///
+/// All packages of the solution are dependencies of the application,
+/// keeping their direct or indirect status, and test dependencies are empty.
+pub fn tests_application(
+    solution: AppDependencies,
+    elm_version: SemVer,
+    source_directories: Vec<String>,
+) -> ApplicationConfig {
+    ApplicationConfig {
+        source_directories,
+        elm_version,
+        dependencies: solution,
+        test_dependencies: AppDependencies {
+            direct: BTreeMap::new(),
+            indirect: BTreeMap::new(),
+        },
+    }
+}
+
+/// Constraints of a package elm.json, allowing the direct dependencies of a solution
+/// up to their next major version, like `elm install` does for packages.
+///
+/// The lower bound of `elm/core` is its major version, like `1.0.0 <= v < 2.0.0`,
+/// since all packages depend on it and a tighter bound only reduces compatibility.
+pub fn package_constraints(solution: &AppDependencies) -> BTreeMap<Pkg, Constraint> {
+    let elm_core = Pkg::new("elm", "core");
+    (solution.direct.iter())
+        .map(|(pkg, version)| {
+            let (major, _, _) = (*version).into();
+            let low = if pkg == &elm_core {
+                SemVer::new(major, 0, 0)
+            } else {
+                *version
+            };
+            let range = Range::between(low, version.bump_major());
+            (pkg.clone(), Constraint(range))
+        })
+        .collect()
+}
+
+/// Application configuration with a subset of the direct dependencies of a solution,
+/// and their dependencies as indirect dependencies, all with the versions of the solution.
+///
+/// The `dependencies` function gives the dependencies of a package version.
+/// Fails if a requested direct dependency, or one of its dependencies, is not in the solution.
+pub fn sub_application(
+    solution: &AppDependencies,
+    direct: &[Pkg],
+    elm_version: SemVer,
+    dependencies: impl Fn(&Pkg, SemVer) -> Result<Vec<Pkg>, Box<dyn Error>>,
+) -> Result<ApplicationConfig, Box<dyn Error>> {
+    let version = |pkg: &Pkg| {
+        (solution.direct.get(pkg))
+            .or_else(|| solution.indirect.get(pkg))
+            .copied()
+            .ok_or_else(|| format!("{} is not in the solution", pkg))
+    };
+    let mut sub_deps = AppDependencies {
+        direct: BTreeMap::new(),
+        indirect: BTreeMap::new(),
+    };
+    for pkg in direct {
+        sub_deps.direct.insert(pkg.clone(), version(pkg)?);
+    }
+    let mut stack: Vec<Pkg> = direct.to_vec();
+    while let Some(pkg) = stack.pop() {
+        for dep in dependencies(&pkg, version(&pkg)?)? {
+            if !sub_deps.direct.contains_key(&dep) && !sub_deps.indirect.contains_key(&dep) {
+                let dep_version =
+                    version(&dep).map_err(|err| format!("{}, but {} depends on it", err, pkg))?;
+                sub_deps.indirect.insert(dep.clone(), dep_version);
+                stack.push(dep);
+            }
+        }
+    }
+    Ok(ApplicationConfig {
+        source_directories: vec!["src".to_string()],
+        elm_version,
+        dependencies: sub_deps,
+        test_dependencies: AppDependencies {
+            direct: BTreeMap::new(),
+            indirect: BTreeMap::new(),
+        },
+    })
+}
+
+/// Graphviz DOT document of the dependency graph of a solution.
+///
+/// Direct dependencies are bold, and test dependencies are gray.
+/// The `dependencies` of each package give the edges,
+/// and the project is an extra `root` node depending on the direct dependencies.
+pub fn dot(
+    solution: &AppDependencies,
+    dependencies: &BTreeMap<String, Vec<String>>,
+    test: &BTreeSet<String>,
+) -> String {
+    let mut dot = String::from("digraph dependencies {\n");
+    dot.push_str("  rankdir=LR;\n  node [shape=box];\n  \"root\" [shape=ellipse];\n");
+    let packages = (solution.direct.iter().map(|(p, v)| (p, v, true)))
+        .chain(solution.indirect.iter().map(|(p, v)| (p, v, false)));
+    for (pkg, version, direct) in packages {
+        let mut attributes = format!("label=\"{}\\n{}\"", pkg, version);
+        if direct {
+            attributes.push_str(", style=bold");
+        }
+        if test.contains(&pkg.to_string()) {
+            attributes.push_str(", color=gray, fontcolor=gray");
+        }
+        writeln!(dot, "  \"{}\" [{}];", pkg, attributes).unwrap();
+    }
+    for pkg in solution.direct.keys() {
+        writeln!(dot, "  \"root\" -> \"{}\";", pkg).unwrap();
+    }
+    for (pkg, deps) in dependencies {
+        for dep in deps {
+            writeln!(dot, "  \"{}\" -> \"{}\";", pkg, dep).unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
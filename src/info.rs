@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Versions and build configuration of this module, for bug reports.
+
+use serde::Serialize;
+
+/// Versions of this crate and of the solver libraries, with the enabled cargo features.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    /// Version of elm-solve-deps-wasm.
+    version: &'static str,
+    /// Version of the elm-solve-deps library.
+    elm_solve_deps: &'static str,
+    /// Version of the pubgrub library.
+    pubgrub: &'static str,
+    /// Cargo features enabled in this build.
+    features: Vec<&'static str>,
+    /// Size profile of this build: `"default"`, `"tiny"` or `"minimal"`.
+    profile: &'static str,
+}
+
+pub fn version_info() -> VersionInfo {
+    let mut features = Vec::new();
+    if cfg!(feature = "wee_alloc") {
+        features.push("wee_alloc");
+    }
+    if cfg!(feature = "tiny") {
+        features.push("tiny");
+    }
+    if cfg!(feature = "minimal") {
+        features.push("minimal");
+    }
+    if cfg!(feature = "console_error_panic_hook") {
+        features.push("console_error_panic_hook");
+    }
+    let profile = if cfg!(feature = "minimal") {
+        "minimal"
+    } else if cfg!(feature = "tiny") {
+        "tiny"
+    } else {
+        "default"
+    };
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        elm_solve_deps: env!("ELM_SOLVE_DEPS_VERSION"),
+        pubgrub: env!("PUBGRUB_VERSION"),
+        features,
+        profile,
+    }
+}
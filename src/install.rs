@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Plans to download and install the packages of a solution.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+
+use elm_solve_deps::project_config::{AppDependencies, Pkg};
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+
+use crate::names;
+use crate::options::Override;
+use crate::provider::JsProvider;
+
+/// Default address of the package registry.
+pub const DEFAULT_REGISTRY_URL: &str = "https://package.elm-lang.org";
+
+/// Artifacts to download for a package of the solution.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    package: Pkg,
+    version: SemVer,
+    /// URL of the `endpoint.json`, containing the URL and hash of the package zipball.
+    endpoint: String,
+    /// URL of the package `elm.json`.
+    elm_json: String,
+    /// URL of the package `docs.json`.
+    docs: String,
+    /// URL of the package zipball, from the endpoint if fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// Hash of the package zipball, from the endpoint if fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+/// Content of the `endpoint.json` of a package version.
+#[derive(Clone, Deserialize)]
+pub struct Endpoint {
+    /// URL of the package zipball.
+    pub url: String,
+    /// SHA-1 hash of the package zipball.
+    pub hash: String,
+}
+
+/// List the artifacts to download for all packages of a solution.
+///
+/// Zipball URLs and hashes are included if the endpoints can be fetched with the provider.
+pub fn download_plan(
+    solution: &AppDependencies,
+    registry_url: &str,
+    provider: &JsProvider,
+) -> Result<Vec<Download>, Box<dyn Error>> {
+    let registry_url = registry_url.trim_end_matches('/');
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| {
+            let base = package_url(registry_url, pkg, *version);
+            let endpoint = provider.fetch_endpoint(pkg, *version)?;
+            Ok(Download {
+                package: pkg.clone(),
+                version: *version,
+                endpoint: format!("{}/endpoint.json", base),
+                elm_json: format!("{}/elm.json", base),
+                docs: format!("{}/docs.json", base),
+                url: endpoint.as_ref().map(|e| e.url.clone()),
+                hash: endpoint.map(|e| e.hash),
+            })
+        })
+        .collect()
+}
+
+/// URL of a package version in the registry, without the empty author
+/// given to package names without author.
+fn package_url(registry_url: &str, pkg: &Pkg, version: SemVer) -> String {
+    let name = pkg.to_string();
+    format!(
+        "{}/packages/{}/{}",
+        registry_url,
+        names::unqualify(&name),
+        version
+    )
+}
+
+/// Where the metadata and the artifact of a package of the solution come from.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Source of the elm.json: `"override"` if given in the options,
+    /// the `source` reported by `fetch_elm_json`, or `"fetchElmJson"`.
+    metadata: String,
+    /// Source of the package zipball: the origin of an override,
+    /// the URL of its endpoint if fetched, or the registry.
+    artifact: String,
+}
+
+/// Where the metadata and artifact of each package of a solution come from.
+pub fn provenance(
+    solution: &AppDependencies,
+    overrides: &HashMap<String, Override>,
+    registry_url: &str,
+    provider: &JsProvider,
+) -> Result<BTreeMap<Pkg, Provenance>, Box<dyn Error>> {
+    let mut provenance = BTreeMap::new();
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let overridden = overrides.get(&pkg.to_string());
+        let metadata = match overridden {
+            Some(o) if o.elm_json.is_some() => "override".to_string(),
+            _ => (provider.source(pkg, *version)).unwrap_or_else(|| "fetchElmJson".to_string()),
+        };
+        let artifact = match overridden {
+            Some(o) => o.origin.clone(),
+            None => match provider.fetch_endpoint(pkg, *version)? {
+                Some(endpoint) => endpoint.url,
+                None => registry_url.to_string(),
+            },
+        };
+        provenance.insert(pkg.clone(), Provenance { metadata, artifact });
+    }
+    Ok(provenance)
+}
+
+/// A package of the solution to install.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Install {
+    package: Pkg,
+    version: SemVer,
+}
+
+/// List the packages of a solution missing locally, according to `has_locally`,
+/// with dependencies before the packages depending on them.
+pub fn install_plan(
+    solution: &AppDependencies,
+    provider: &JsProvider,
+) -> Result<Vec<Install>, Box<dyn Error>> {
+    let versions: BTreeMap<&Pkg, SemVer> = (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(p, v)| (p, *v))
+        .collect();
+    let mut ordered = Vec::new();
+    let mut visited = HashSet::new();
+    for pkg in versions.keys() {
+        visit(pkg, &versions, provider, &mut visited, &mut ordered)?;
+    }
+    let mut plan = Vec::new();
+    for pkg in ordered {
+        let version = versions[&pkg];
+        if !provider.has_locally(&pkg, version)? {
+            plan.push(Install {
+                package: pkg,
+                version,
+            });
+        }
+    }
+    Ok(plan)
+}
+
+/// Depth-first traversal adding dependencies to `ordered` before their dependents.
+fn visit(
+    pkg: &Pkg,
+    versions: &BTreeMap<&Pkg, SemVer>,
+    provider: &JsProvider,
+    visited: &mut HashSet<Pkg>,
+    ordered: &mut Vec<Pkg>,
+) -> Result<(), Box<dyn Error>> {
+    if !visited.insert(pkg.clone()) {
+        return Ok(());
+    }
+    if let Some(version) = versions.get(pkg) {
+        let pkg_config = provider.fetch_elm_json(pkg, *version)?;
+        for dep in pkg_config.dependencies.keys() {
+            visit(dep, versions, provider, visited, ordered)?;
+        }
+        ordered.push(pkg.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn url_of_package() {
+        let pkg = Pkg::from_str("elm/core").unwrap();
+        let url = package_url(DEFAULT_REGISTRY_URL, &pkg, SemVer::new(1, 0, 5));
+        assert_eq!(url, "https://package.elm-lang.org/packages/elm/core/1.0.5");
+    }
+
+    #[test]
+    fn url_of_package_without_author() {
+        let pkg = Pkg::from_str(&names::qualify("core")).unwrap();
+        let url = package_url("https://registry.example.com", &pkg, SemVer::new(1, 0, 5));
+        assert_eq!(url, "https://registry.example.com/packages/core/1.0.5");
+    }
+}
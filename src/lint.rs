@@ -0,0 +1,453 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Checks of elm.json files, without solving dependencies.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use elm_solve_deps::project_config::{
+    AppDependencies, ApplicationConfig, PackageConfig, Pkg, ProjectConfig,
+};
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+/// A package that is resolved differently in the dependencies and test dependencies.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestConsistencyConflict {
+    /// The package in conflict.
+    package: Pkg,
+    /// Version (or constraint for a package) in the dependencies.
+    dependencies: String,
+    /// Version (or constraint for a package) in the test dependencies.
+    test_dependencies: String,
+}
+
+/// Check that test dependencies never resolve a package to a different version
+/// than the normal dependencies.
+///
+/// For an application, the versions recorded in both sections must be the same.
+/// For a package, the constraints of both sections must intersect.
+pub fn test_consistency(project_elm_json: &ProjectConfig) -> Vec<TestConsistencyConflict> {
+    match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            let deps = &app_config.dependencies;
+            let test_deps = &app_config.test_dependencies;
+            let app_versions: BTreeMap<_, _> =
+                deps.direct.iter().chain(deps.indirect.iter()).collect();
+            (test_deps.direct.iter())
+                .chain(test_deps.indirect.iter())
+                .filter_map(|(pkg, test_version)| {
+                    let version = app_versions.get(pkg)?;
+                    (*version != test_version).then(|| TestConsistencyConflict {
+                        package: pkg.clone(),
+                        dependencies: version.to_string(),
+                        test_dependencies: test_version.to_string(),
+                    })
+                })
+                .collect()
+        }
+        ProjectConfig::Package(pkg_config) => (pkg_config.test_dependencies.iter())
+            .filter_map(|(pkg, test_constraint)| {
+                let constraint = pkg_config.dependencies.get(pkg)?;
+                let intersection = constraint.0.intersection(&test_constraint.0);
+                (intersection.lowest_version().is_none()).then(|| TestConsistencyConflict {
+                    package: pkg.clone(),
+                    dependencies: constraint.0.to_string(),
+                    test_dependencies: test_constraint.0.to_string(),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// A problem found in an elm.json.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    /// The elm.json field with the problem, like `dependencies`.
+    pub field: String,
+    /// The package concerned, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Finding {
+    /// Create a finding about a field, and possibly a package.
+    pub fn new(field: &str, package: Option<&str>, message: String) -> Self {
+        Self {
+            field: field.to_string(),
+            package: package.map(|p| p.to_string()),
+            message,
+        }
+    }
+}
+
+/// Check the shape of the constraints of a package elm.json, like `elm publish` requires.
+///
+/// Constraints must have the form `low <= v < high`, with an upper bound
+/// that is a major version, like `1.0.0 <= v < 2.0.0`, and the targeted elm version
+/// must be allowed by the `elm-version` range, whatever that range is.
+/// The elm.json is checked as raw JSON since badly shaped constraints fail to decode.
+pub fn publishable(elm_json: &serde_json::Value, elm_version: SemVer) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if elm_json["type"] != "package" {
+        findings.push(Finding::new(
+            "type",
+            None,
+            "Only packages can be published".to_string(),
+        ));
+        return findings;
+    }
+
+    match elm_json["elm-version"].as_str() {
+        None => findings.push(Finding::new(
+            "elm-version",
+            None,
+            "The elm version constraint is missing".to_string(),
+        )),
+        Some(constraint) => match check_constraint(constraint) {
+            Err(message) => findings.push(Finding::new("elm-version", None, message)),
+            Ok((low, high)) => {
+                if !(low <= elm_version && elm_version < high) {
+                    findings.push(Finding::new(
+                        "elm-version",
+                        None,
+                        format!(
+                            "The constraint {} does not allow elm {}",
+                            constraint, elm_version
+                        ),
+                    ));
+                }
+            }
+        },
+    }
+
+    for field in ["dependencies", "test-dependencies"] {
+        let deps = match elm_json[field].as_object() {
+            Some(deps) => deps,
+            None => {
+                findings.push(Finding::new(
+                    field,
+                    None,
+                    format!("The {} field must be an object", field),
+                ));
+                continue;
+            }
+        };
+        for (pkg, constraint) in deps {
+            if let Err(err) = Pkg::from_str(pkg) {
+                findings.push(Finding::new(field, Some(pkg), err.to_string()));
+            }
+            let message = match constraint.as_str() {
+                None => Some("The constraint must be a string".to_string()),
+                Some(constraint) => check_dependency_constraint(constraint).err(),
+            };
+            if let Some(message) = message {
+                findings.push(Finding::new(field, Some(pkg), message));
+            }
+        }
+    }
+    findings
+}
+
+/// Check that a constraint has the form `low <= v < high`, and return its bounds.
+fn check_constraint(constraint: &str) -> Result<(SemVer, SemVer), String> {
+    let bounds = constraint
+        .split_once(" <= v < ")
+        .and_then(|(low, high)| Some((SemVer::from_str(low).ok()?, SemVer::from_str(high).ok()?)));
+    let (low, high) = bounds.ok_or_else(|| {
+        format!(
+            "The constraint {} must have the form 1.0.0 <= v < 2.0.0",
+            constraint
+        )
+    })?;
+    if low >= high {
+        return Err(format!("The constraint {} allows no version", constraint));
+    }
+    Ok((low, high))
+}
+
+/// Check that a dependency constraint has the form `low <= v < high`,
+/// with an upper bound that is a major version.
+fn check_dependency_constraint(constraint: &str) -> Result<(), String> {
+    let (low, high) = check_constraint(constraint)?;
+    let (_, minor, patch) = high.into();
+    if (minor, patch) != (0, 0) {
+        return Err(format!(
+            "The upper bound of the constraint {} must be a major version, like {}",
+            constraint,
+            low.bump_major()
+        ));
+    }
+    Ok(())
+}
+
+/// Find packages listed in several sections of an elm.json.
+///
+/// A package must appear only once among the direct and indirect dependencies
+/// and test dependencies of an application,
+/// or among the dependencies and test dependencies of a package.
+pub fn duplicates(project_elm_json: &ProjectConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            let sections = [
+                ("dependencies.direct", &app_config.dependencies.direct),
+                ("dependencies.indirect", &app_config.dependencies.indirect),
+                (
+                    "test-dependencies.direct",
+                    &app_config.test_dependencies.direct,
+                ),
+                (
+                    "test-dependencies.indirect",
+                    &app_config.test_dependencies.indirect,
+                ),
+            ];
+            for (i, (field, deps)) in sections.iter().enumerate() {
+                for (pkg, version) in deps.iter() {
+                    for (other_field, other_deps) in &sections[..i] {
+                        let other_version = match other_deps.get(pkg) {
+                            Some(other_version) => other_version,
+                            None => continue,
+                        };
+                        let message = if other_version == version {
+                            format!("{} is already listed in {}", pkg, other_field)
+                        } else {
+                            format!(
+                                "{} is already listed in {} with the contradictory version {}",
+                                pkg, other_field, other_version
+                            )
+                        };
+                        findings.push(Finding::new(field, Some(&pkg.to_string()), message));
+                    }
+                }
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            for pkg in pkg_config.test_dependencies.keys() {
+                if pkg_config.dependencies.contains_key(pkg) {
+                    findings.push(Finding::new(
+                        "test-dependencies",
+                        Some(&pkg.to_string()),
+                        format!("{} is already listed in dependencies", pkg),
+                    ));
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Fix the layout of the dependencies of an application.
+///
+/// Indirect entries duplicating direct ones are removed, keeping the direct version,
+/// test dependencies already in the dependencies are removed,
+/// and the `needed_direct` packages only listed as indirect dependencies become direct.
+/// Return the findings, each corresponding to a fix in the returned configuration.
+pub fn fix_layout(
+    app_config: &ApplicationConfig,
+    needed_direct: &[Pkg],
+) -> (Vec<Finding>, ApplicationConfig) {
+    let mut findings = Vec::new();
+    let mut fixed = app_config.clone();
+    let deps = &mut fixed.dependencies;
+    let test_deps = &mut fixed.test_dependencies;
+
+    // Indirect entries shadowing direct ones.
+    for (field, deps) in [
+        ("dependencies.indirect", &mut *deps),
+        ("test-dependencies.indirect", &mut *test_deps),
+    ] {
+        let direct = &deps.direct;
+        deps.indirect.retain(|pkg, _| {
+            let shadowed = direct.contains_key(pkg);
+            if shadowed {
+                findings.push(Finding::new(
+                    field,
+                    Some(&pkg.to_string()),
+                    format!(
+                        "{} is already a direct dependency, removed from indirect",
+                        pkg
+                    ),
+                ));
+            }
+            !shadowed
+        });
+    }
+
+    // Test dependencies already in dependencies.
+    for (field, test_section) in [
+        ("test-dependencies.direct", &mut test_deps.direct),
+        ("test-dependencies.indirect", &mut test_deps.indirect),
+    ] {
+        test_section.retain(|pkg, _| {
+            let duplicate = deps.direct.contains_key(pkg) || deps.indirect.contains_key(pkg);
+            if duplicate {
+                findings.push(Finding::new(
+                    field,
+                    Some(&pkg.to_string()),
+                    format!(
+                        "{} is already in dependencies, removed from test-dependencies",
+                        pkg
+                    ),
+                ));
+            }
+            !duplicate
+        });
+    }
+
+    // Needed direct dependencies only listed as indirect.
+    for pkg in needed_direct {
+        if let Some(version) = deps.indirect.remove(pkg) {
+            deps.direct.insert(pkg.clone(), version);
+            findings.push(Finding::new(
+                "dependencies.indirect",
+                Some(&pkg.to_string()),
+                format!("{} is needed as a direct dependency, moved to direct", pkg),
+            ));
+        }
+    }
+    (findings, fixed)
+}
+
+/// A constraint that a proposed solution does not satisfy.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    /// The package concerned.
+    pub package: Pkg,
+    /// What requires the package: `project`, or a package version like `elm/http@2.0.0`.
+    pub required_by: String,
+    /// Description of the problem.
+    pub message: String,
+}
+
+/// Packages required, with their allowed range of versions.
+type Requirements<'a> = Vec<(&'a Pkg, Range<SemVer>)>;
+
+/// Check that a solution satisfies all the constraints of the project,
+/// and of the elm.json of each package in the solution, given by `elm_json`.
+///
+/// This does not solve anything, so it can serve as an independent oracle for the solver.
+/// For the test dependencies of the project, only those in the solution are checked.
+pub fn solution_violations(
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+    mut elm_json: impl FnMut(&Pkg, SemVer) -> Result<Rc<PackageConfig>, String>,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut violation = |package: &Pkg, required_by: &str, message: String| {
+        violations.push(Violation {
+            package: package.clone(),
+            required_by: required_by.to_string(),
+            message,
+        })
+    };
+    for pkg in solution.direct.keys() {
+        if solution.indirect.contains_key(pkg) {
+            violation(
+                pkg,
+                "project",
+                format!("{} is both direct and indirect", pkg),
+            );
+        }
+    }
+    let versions: BTreeMap<&Pkg, SemVer> = (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(p, v)| (p, *v))
+        .collect();
+    let mut check = |pkg: &Pkg, range: &Range<SemVer>, required_by: &str| match versions.get(pkg) {
+        None => violation(
+            pkg,
+            required_by,
+            format!(
+                "{} requires {} {} but it is not in the solution",
+                required_by, pkg, range
+            ),
+        ),
+        Some(version) if !range.contains(version) => violation(
+            pkg,
+            required_by,
+            format!(
+                "{} requires {} {} but the solution has {}",
+                required_by, pkg, range, version
+            ),
+        ),
+        Some(_) => {}
+    };
+
+    // Constraints of the project.
+    let (normal_deps, test_deps): (Requirements, Requirements) = match project_elm_json {
+        ProjectConfig::Application(app_config) => (
+            (app_config.dependencies.direct.iter())
+                .map(|(p, v)| (p, Range::exact(*v)))
+                .collect(),
+            (app_config.test_dependencies.direct.iter())
+                .map(|(p, v)| (p, Range::exact(*v)))
+                .collect(),
+        ),
+        ProjectConfig::Package(pkg_config) => (
+            (pkg_config.dependencies.iter())
+                .map(|(p, c)| (p, c.0.clone()))
+                .collect(),
+            (pkg_config.test_dependencies.iter())
+                .map(|(p, c)| (p, c.0.clone()))
+                .collect(),
+        ),
+    };
+    for (pkg, range) in &normal_deps {
+        check(pkg, range, "project");
+    }
+    for (pkg, range) in &test_deps {
+        if versions.contains_key(pkg) {
+            check(pkg, range, "project");
+        }
+    }
+
+    // Constraints of each package of the solution.
+    let mut unknown = Vec::new();
+    for (pkg, version) in &versions {
+        let required_by = format!("{}@{}", pkg, version);
+        match elm_json(pkg, *version) {
+            Ok(pkg_config) => {
+                for (dep, constraint) in &pkg_config.dependencies {
+                    check(dep, &constraint.0, &required_by);
+                }
+            }
+            Err(message) => unknown.push((*pkg, required_by, message)),
+        }
+    }
+
+    // Direct dependencies must be the ones of the project.
+    let project_direct: Vec<&Pkg> = (normal_deps.iter())
+        .chain(test_deps.iter())
+        .map(|(p, _)| *p)
+        .collect();
+    for pkg in solution.direct.keys() {
+        if !project_direct.contains(&pkg) {
+            violation(
+                pkg,
+                "project",
+                format!("{} is direct but the project does not depend on it", pkg),
+            );
+        }
+    }
+    for (pkg, _) in &normal_deps {
+        if solution.indirect.contains_key(pkg) && !solution.direct.contains_key(pkg) {
+            violation(
+                pkg,
+                "project",
+                format!("{} is a dependency of the project but is indirect", pkg),
+            );
+        }
+    }
+    for (pkg, required_by, message) in unknown {
+        violation(pkg, &required_by, message);
+    }
+    violations
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Merge of the dependencies of two elm.json files, intersecting their constraints.
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::project_config::Pkg;
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::constraints::{self, AdditionalConstraint};
+
+/// Package whose constraints in the two merged sections have an empty intersection.
+#[derive(Serialize)]
+pub struct MergeConflict {
+    package: Pkg,
+    /// Constraint of the package in the first section.
+    a: AdditionalConstraint,
+    /// Constraint of the package in the second section.
+    b: AdditionalConstraint,
+}
+
+/// Result of the merge of two dependency sections.
+#[derive(Serialize)]
+pub struct Merged {
+    /// Intersection of the constraints of each package, except for conflicts,
+    /// with the syntax of additional constraints.
+    dependencies: BTreeMap<Pkg, AdditionalConstraint>,
+    conflicts: Vec<MergeConflict>,
+}
+
+/// Merge two dependency sections by intersecting the ranges of packages in both.
+///
+/// Packages in only one section keep their range,
+/// and packages whose ranges do not intersect are reported as conflicts.
+pub fn merge_dependencies(
+    a: &BTreeMap<Pkg, Range<SemVer>>,
+    b: &BTreeMap<Pkg, Range<SemVer>>,
+) -> Merged {
+    let mut dependencies = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    for (pkg, range_a) in a {
+        let range = match b.get(pkg) {
+            Some(range_b) => range_a.intersection(range_b),
+            None => range_a.clone(),
+        };
+        if range == Range::none() {
+            conflicts.push(MergeConflict {
+                package: pkg.clone(),
+                a: constraints::format(range_a),
+                b: constraints::format(&b[pkg]),
+            });
+        } else {
+            dependencies.insert(pkg.clone(), constraints::format(&range));
+        }
+    }
+    for (pkg, range_b) in b {
+        if !a.contains_key(pkg) {
+            dependencies.insert(pkg.clone(), constraints::format(range_b));
+        }
+    }
+    Merged {
+        dependencies,
+        conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn dependencies(deps: &[(&str, &str)]) -> BTreeMap<Pkg, Range<SemVer>> {
+        (deps.iter())
+            .map(|(pkg, constraint)| {
+                let range = constraints::parse(constraint).unwrap().0;
+                (Pkg::from_str(pkg).unwrap(), range)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn intersect_common_packages() {
+        let a = dependencies(&[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("elm/json", "1.0.0 <= v < 2.0.0"),
+        ]);
+        let b = dependencies(&[
+            ("elm/core", "1.0.2 <= v < 3.0.0 && != 1.0.4"),
+            ("elm/http", "2.0.0 <= v < 3.0.0"),
+        ]);
+        let merged = serde_json::to_value(merge_dependencies(&a, &b)).unwrap();
+        assert_eq!(
+            merged,
+            json!({
+                "dependencies": {
+                    "elm/core": "1.0.2 <= v < 2.0.0 && != 1.0.4",
+                    "elm/http": "2.0.0 <= v < 3.0.0",
+                    "elm/json": "1.0.0 <= v < 2.0.0",
+                },
+                "conflicts": [],
+            })
+        );
+    }
+
+    #[test]
+    fn report_conflicts() {
+        let a = dependencies(&[("elm/core", "1.0.0 <= v < 2.0.0")]);
+        let b = dependencies(&[("elm/core", "0.0.0 <= v < 1.0.0")]);
+        let merged = serde_json::to_value(merge_dependencies(&a, &b)).unwrap();
+        assert_eq!(
+            merged,
+            json!({
+                "dependencies": {},
+                "conflicts": [{
+                    "package": "elm/core",
+                    "a": "1.0.0 <= v < 2.0.0",
+                    "b": "0.0.0 <= v < 1.0.0",
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn keep_unions() {
+        let a = dependencies(&[("elm/core", "1.0.0 <= v < 3.0.0")]);
+        let range = Range::between(SemVer::new(1, 0, 0), SemVer::new(1, 1, 0))
+            .union(&Range::between(SemVer::new(2, 0, 0), SemVer::new(4, 0, 0)));
+        let b = BTreeMap::from([(Pkg::from_str("elm/core").unwrap(), range)]);
+        let merged = serde_json::to_value(merge_dependencies(&a, &b)).unwrap();
+        assert_eq!(
+            merged["dependencies"]["elm/core"],
+            json!(["1.0.0 <= v < 1.1.0", "2.0.0 <= v < 3.0.0"])
+        );
+    }
+}
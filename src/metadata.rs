@@ -0,0 +1,384 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Information about the packages of a solution, gathered from their elm.json.
+//!
+//! The elm.json of all packages of a solution were fetched during the solve,
+//! so they are read from the provider cache, without calling JS again.
+//! Versions of packages constrained to an exact version were not listed during the solve,
+//! so those may still be requested to JS.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::constraints::{self, AdditionalConstraint};
+use crate::provider::JsProvider;
+use crate::solver;
+
+/// Group the packages of a solution by license.
+pub fn licenses(
+    solution: &AppDependencies,
+    provider: &JsProvider,
+) -> Result<BTreeMap<String, Vec<Pkg>>, Box<dyn Error>> {
+    let mut licenses: BTreeMap<String, Vec<Pkg>> = BTreeMap::new();
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let pkg_config = provider.fetch_elm_json(pkg, *version)?;
+        licenses
+            .entry(pkg_config.license.clone())
+            .or_default()
+            .push(pkg.clone());
+    }
+    for packages in licenses.values_mut() {
+        packages.sort();
+    }
+    Ok(licenses)
+}
+
+/// Summary of a solution.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    /// Number of packages in the solution.
+    package_count: usize,
+    /// Number of direct dependencies.
+    direct_count: usize,
+    /// Number of indirect dependencies.
+    indirect_count: usize,
+    /// Length of the longest chain of dependencies, 1 if there are only direct dependencies.
+    max_depth: usize,
+    /// Intersection of the `elm-version` ranges of all packages.
+    elm_version: String,
+}
+
+/// Summarize a solution.
+pub fn summary(
+    solution: &AppDependencies,
+    provider: &JsProvider,
+) -> Result<Summary, Box<dyn Error>> {
+    let versions: BTreeMap<&Pkg, _> = solution
+        .direct
+        .iter()
+        .chain(solution.indirect.iter())
+        .collect();
+    let mut elm_version = Range::any();
+    let mut dependencies = HashMap::new();
+    for (pkg, version) in &versions {
+        let pkg_config = provider.fetch_elm_json(pkg, **version)?;
+        elm_version = elm_version.intersection(&pkg_config.elm_version.0);
+        let deps: Vec<Pkg> = pkg_config.dependencies.keys().cloned().collect();
+        dependencies.insert(*pkg, deps);
+    }
+    let mut depths = HashMap::new();
+    let max_depth = (solution.direct.keys())
+        .map(|pkg| depth(pkg, &dependencies, &mut depths))
+        .max()
+        .unwrap_or(0);
+    Ok(Summary {
+        package_count: versions.len(),
+        direct_count: solution.direct.len(),
+        indirect_count: solution.indirect.len(),
+        max_depth,
+        elm_version: elm_version.to_string(),
+    })
+}
+
+/// Compatibility of a solution with elm versions.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElmCompatibility {
+    /// Intersection of the `elm-version` ranges of all packages.
+    elm_version: String,
+    /// True if no elm version is compatible with all packages.
+    empty: bool,
+    /// True if the elm version declared by the project is compatible with all packages.
+    /// For a package, at least one version of its `elm-version` range must be.
+    allows_project: bool,
+}
+
+/// Intersect the `elm-version` ranges of all packages of a solution,
+/// and check it against the elm version declared by the project.
+pub fn elm_compatibility(
+    solution: &AppDependencies,
+    project_elm_json: &ProjectConfig,
+    provider: &JsProvider,
+) -> Result<ElmCompatibility, Box<dyn Error>> {
+    let mut elm_version = Range::any();
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let pkg_config = provider.fetch_elm_json(pkg, *version)?;
+        elm_version = elm_version.intersection(&pkg_config.elm_version.0);
+    }
+    let empty = elm_version == Range::none();
+    let allows_project = match project_elm_json {
+        ProjectConfig::Application(app_config) => elm_version.contains(&app_config.elm_version),
+        ProjectConfig::Package(pkg_config) => {
+            elm_version.intersection(&pkg_config.elm_version.0) != Range::none()
+        }
+    };
+    if empty {
+        log::warn!("No elm version is compatible with all the packages of the solution");
+    } else if !allows_project {
+        log::warn!(
+            "The elm version of the project is not compatible with all the packages of the solution, only {} is",
+            elm_version
+        );
+    }
+    Ok(ElmCompatibility {
+        elm_version: elm_version.to_string(),
+        empty,
+        allows_project,
+    })
+}
+
+/// Package of a solution incompatible with an elm version.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElmIncompatibility {
+    package: Pkg,
+    version: SemVer,
+    /// The `elm-version` range of the package, excluding the elm version.
+    elm_version: String,
+}
+
+/// List the packages of a solution whose `elm-version` range excludes the given elm version.
+pub fn incompatible_packages(
+    solution: &AppDependencies,
+    elm_version: SemVer,
+    provider: &JsProvider,
+) -> Result<Vec<ElmIncompatibility>, Box<dyn Error>> {
+    let versions: BTreeMap<&Pkg, &SemVer> = (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .collect();
+    let mut incompatible = Vec::new();
+    for (pkg, version) in versions {
+        let pkg_config = provider.fetch_elm_json(pkg, *version)?;
+        if !pkg_config.elm_version.0.contains(&elm_version) {
+            incompatible.push(ElmIncompatibility {
+                package: pkg.clone(),
+                version: *version,
+                elm_version: pkg_config.elm_version.0.to_string(),
+            });
+        }
+    }
+    Ok(incompatible)
+}
+
+/// Length of the longest chain of dependencies starting at a package, memoized in `depths`.
+fn depth<'a>(
+    pkg: &'a Pkg,
+    dependencies: &'a HashMap<&Pkg, Vec<Pkg>>,
+    depths: &mut HashMap<&'a Pkg, usize>,
+) -> usize {
+    if let Some(depth) = depths.get(pkg) {
+        return *depth;
+    }
+    let deps = dependencies.get(pkg).map(Vec::as_slice).unwrap_or_default();
+    let depth = 1
+        + (deps.iter())
+            .map(|dep| depth(dep, dependencies, depths))
+            .max()
+            .unwrap_or(0);
+    depths.insert(pkg, depth);
+    depth
+}
+
+/// How far behind the latest version a package of the solution is.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Freshness {
+    /// Version in the solution.
+    current: SemVer,
+    /// Latest version published.
+    latest: SemVer,
+    /// Number of versions published after the current one.
+    versions_behind: usize,
+    /// Time between the releases of the current and latest versions,
+    /// in the unit of the release dates, if both are known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_behind: Option<f64>,
+}
+
+/// Compute how far behind the latest version each package of the solution is.
+///
+/// `release_dates` maps packages to the release dates of their versions.
+pub fn freshness(
+    solution: &AppDependencies,
+    provider: &JsProvider,
+    release_dates: &HashMap<String, HashMap<String, f64>>,
+) -> Result<BTreeMap<Pkg, Freshness>, Box<dyn Error>> {
+    // Consider all versions, not only the most recent ones.
+    provider.remove_versions_cap();
+    let mut freshness = BTreeMap::new();
+    for (pkg, &current) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let newer: Vec<SemVer> = provider
+            .list_available_versions(pkg)?
+            .filter(|v| v > &current)
+            .collect();
+        let latest = newer.iter().max().copied().unwrap_or(current);
+        let dates = release_dates.get(&pkg.to_string());
+        let date_of = |v: SemVer| dates.and_then(|d| d.get(&v.to_string())).copied();
+        let time_behind = match (date_of(current), date_of(latest)) {
+            (Some(current_date), Some(latest_date)) => Some(latest_date - current_date),
+            _ => None,
+        };
+        freshness.insert(
+            pkg.clone(),
+            Freshness {
+                current,
+                latest,
+                versions_behind: newer.len(),
+                time_behind,
+            },
+        );
+    }
+    Ok(freshness)
+}
+
+/// Package of a solution with a license not allowed.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseViolation {
+    package: Pkg,
+    version: SemVer,
+    license: String,
+    /// Packages pulling it in, from a direct dependency of the project to the package itself.
+    path: Vec<Pkg>,
+}
+
+impl fmt::Display for LicenseViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path: Vec<String> = self.path.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "{} {} has the license {}, pulled in by {}",
+            self.package,
+            self.version,
+            self.license,
+            path.join(" -> ")
+        )
+    }
+}
+
+/// List the packages of a solution whose license is not in `allowed` (unless empty),
+/// or is in `denied`, with the shortest path of dependencies pulling them in.
+pub fn license_violations(
+    solution: &AppDependencies,
+    allowed: &[String],
+    denied: &[String],
+    provider: &JsProvider,
+) -> Result<Vec<LicenseViolation>, Box<dyn Error>> {
+    let graph = graph(solution, provider)?;
+    // Breadth-first search from the direct dependencies, for the shortest paths.
+    let mut parents: BTreeMap<&Pkg, Option<&Pkg>> =
+        solution.direct.keys().map(|p| (p, None)).collect();
+    let mut queue: VecDeque<&Pkg> = solution.direct.keys().collect();
+    while let Some(pkg) = queue.pop_front() {
+        for dep in graph.get(pkg).into_iter().flatten() {
+            if !parents.contains_key(dep) {
+                parents.insert(dep, Some(pkg));
+                queue.push_back(dep);
+            }
+        }
+    }
+    let mut violations = Vec::new();
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let license = provider.fetch_elm_json(pkg, *version)?.license.clone();
+        if (allowed.is_empty() || allowed.contains(&license)) && !denied.contains(&license) {
+            continue;
+        }
+        let mut path = vec![pkg.clone()];
+        let mut current = pkg;
+        while let Some(Some(parent)) = parents.get(current) {
+            path.push((*parent).clone());
+            current = parent;
+        }
+        path.reverse();
+        violations.push(LicenseViolation {
+            package: pkg.clone(),
+            version: *version,
+            license,
+            path,
+        });
+    }
+    Ok(violations)
+}
+
+/// Dependencies of each package of the solution.
+pub fn graph(
+    solution: &AppDependencies,
+    provider: &JsProvider,
+) -> Result<BTreeMap<Pkg, Vec<Pkg>>, Box<dyn Error>> {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| {
+            let pkg_config = provider.fetch_elm_json(pkg, *version)?;
+            Ok((
+                pkg.clone(),
+                pkg_config.dependencies.keys().cloned().collect(),
+            ))
+        })
+        .collect()
+}
+
+/// Constraints imposed on a package by its dependents in a solution.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstraintsOn {
+    package: Pkg,
+    /// Each dependent with the constraint it imposes.
+    constraints: Vec<DependentConstraint>,
+    /// Intersection of all the constraints.
+    intersection: AdditionalConstraint,
+}
+
+/// Constraint imposed on a package by one of its dependents.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependentConstraint {
+    /// The dependent package, or `root` for the project itself.
+    dependent: String,
+    /// Version of the dependent in the solution, none for the project itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<SemVer>,
+    constraint: AdditionalConstraint,
+}
+
+/// List the constraints imposed on a package by the project and the packages of its solution.
+pub fn constraints_on(
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+    package: &Pkg,
+    provider: &JsProvider,
+) -> Result<ConstraintsOn, Box<dyn Error>> {
+    let mut constraints = Vec::new();
+    let mut intersection = Range::any();
+    let project_deps = solver::direct_dependencies(project_elm_json, true, &[]);
+    if let Some(range) = project_deps.get(package) {
+        intersection = intersection.intersection(range);
+        constraints.push(DependentConstraint {
+            dependent: "root".to_string(),
+            version: None,
+            constraint: constraints::format(range),
+        });
+    }
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let pkg_config = provider.fetch_elm_json(pkg, *version)?;
+        if let Some(constraint) = pkg_config.dependencies.get(package) {
+            intersection = intersection.intersection(&constraint.0);
+            constraints.push(DependentConstraint {
+                dependent: pkg.to_string(),
+                version: Some(*version),
+                constraint: constraints::format(&constraint.0),
+            });
+        }
+    }
+    Ok(ConstraintsOn {
+        package: package.clone(),
+        constraints,
+        intersection: constraints::format(&intersection),
+    })
+}
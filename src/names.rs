@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Package names without author, for private registries with other naming conventions.
+//!
+//! The elm-solve-deps crate requires a `/` between the author and the package name.
+//! So names without author are given an empty author while solving, like `/pkg`,
+//! and the empty author is removed from everything given back to JS.
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+
+/// Give an empty author to a package name without author.
+pub fn qualify(name: &str) -> String {
+    if name.contains('/') {
+        name.to_string()
+    } else {
+        format!("/{}", name)
+    }
+}
+
+/// Remove the empty author given by `qualify`.
+pub fn unqualify(name: &str) -> &str {
+    match name.strip_prefix('/') {
+        Some(unqualified) if !unqualified.contains('/') => unqualified,
+        _ => name,
+    }
+}
+
+/// Give an empty author to the package names without author of an elm.json,
+/// in its name and in the keys of its dependencies.
+pub fn qualify_elm_json(elm_json: &str) -> serde_json::Result<String> {
+    let mut config: Value = serde_json::from_str(elm_json)?;
+    if let Some(Value::String(name)) = config.get_mut("name") {
+        *name = qualify(name);
+    }
+    let is_application = config.get("type").and_then(Value::as_str) == Some("application");
+    for field in ["dependencies", "test-dependencies"] {
+        let dependencies = match config.get_mut(field) {
+            Some(Value::Object(dependencies)) => dependencies,
+            _ => continue,
+        };
+        if !is_application {
+            qualify_keys(dependencies);
+            continue;
+        }
+        for half in ["direct", "indirect"] {
+            if let Some(Value::Object(half)) = dependencies.get_mut(half) {
+                qualify_keys(half);
+            }
+        }
+    }
+    Ok(config.to_string())
+}
+
+/// Give an empty author to the keys of a map of dependencies.
+fn qualify_keys(dependencies: &mut Map<String, Value>) {
+    *dependencies = std::mem::take(dependencies)
+        .into_iter()
+        .map(|(pkg, value)| (qualify(&pkg), value))
+        .collect();
+}
+
+/// Remove the empty author of the given packages everywhere in a JSON value,
+/// in object keys and in strings.
+pub fn unqualify_json(value: &mut Value, qualified: &HashSet<String>) {
+    let unqualify_known = |name: &str| {
+        if qualified.contains(name) {
+            unqualify(name).to_string()
+        } else {
+            name.to_string()
+        }
+    };
+    match value {
+        Value::String(name) => *name = unqualify_known(name),
+        Value::Array(values) => {
+            for value in values {
+                unqualify_json(value, qualified);
+            }
+        }
+        Value::Object(map) => {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    unqualify_json(&mut value, qualified);
+                    (unqualify_known(&key), value)
+                })
+                .collect();
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Search of the best solution according to an objective, within a budget of solves.
+
+use std::error::Error;
+
+use elm_solve_deps::project_config::{AppDependencies, Pkg};
+use pubgrub::error::PubGrubError;
+use pubgrub::version::SemanticVersion as SemVer;
+
+use crate::options::Objective;
+use crate::provider::JsProvider;
+
+/// Default maximum number of additional solves when optimizing.
+pub const DEFAULT_BUDGET: u32 = 20;
+
+/// Improve a solution by excluding the chosen versions one at a time and solving again,
+/// keeping the new solution each time it has a better score, until no exclusion improves it
+/// or the budget of solves is spent.
+///
+/// The `solve` function solves with the given package versions excluded,
+/// and `score` gives the score of a solution, higher is better.
+/// Packages in `fixed` are never excluded, like the direct dependencies of applications.
+pub fn improve<S, F>(
+    initial: AppDependencies,
+    fixed: &[Pkg],
+    budget: u32,
+    solve: S,
+    score: F,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>
+where
+    S: Fn(&[(Pkg, SemVer)]) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>,
+    F: Fn(&AppDependencies) -> Result<f64, Box<dyn Error>>,
+{
+    let score = |solution: &AppDependencies| {
+        score(solution)
+            .map_err(|err| PubGrubError::Failure(format!("Failed to score a solution: {}", err)))
+    };
+    let mut best_score = score(&initial)?;
+    let mut best = initial;
+    let mut exclusions: Vec<(Pkg, SemVer)> = Vec::new();
+    let mut solves = 0;
+    'search: while solves < budget {
+        let chosen: Vec<(Pkg, SemVer)> = (best.direct.iter())
+            .chain(best.indirect.iter())
+            .filter(|(p, _)| !fixed.contains(p))
+            .map(|(p, v)| (p.clone(), *v))
+            .collect();
+        for (pkg, version) in chosen {
+            if solves >= budget {
+                break 'search;
+            }
+            solves += 1;
+            let mut candidate = exclusions.clone();
+            candidate.push((pkg, version));
+            let solution = match solve(&candidate) {
+                Ok(solution) => solution,
+                Err(PubGrubError::NoSolution(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let solution_score = score(&solution)?;
+            log::debug!("Candidate solution with a score of {}", solution_score);
+            if solution_score > best_score {
+                best = solution;
+                best_score = solution_score;
+                exclusions = candidate;
+                continue 'search;
+            }
+        }
+        break;
+    }
+    log::info!(
+        "Best solution score: {} after {} solves",
+        best_score,
+        solves
+    );
+    Ok(best)
+}
+
+/// Score of a solution for a built-in objective.
+pub fn objective_score(
+    objective: Objective,
+    solution: &AppDependencies,
+    provider: &JsProvider,
+) -> Result<f64, Box<dyn Error>> {
+    let versions = solution.direct.iter().chain(solution.indirect.iter());
+    match objective {
+        Objective::FewestPackages => Ok(-(versions.count() as f64)),
+        Objective::Recency => {
+            // Number of newer versions of each package, summed over the solution.
+            let mut behind = 0;
+            for (pkg, version) in versions {
+                behind += (provider.list_available_versions(pkg)?)
+                    .filter(|v| v > version)
+                    .count();
+            }
+            Ok(-(behind as f64))
+        }
+    }
+}
@@ -0,0 +1,465 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Options of the solver, provided as an optional JS object,
+//! or as JSON by the hosts of the `wasi` module.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Deserializer, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::names;
+
+/// Options accepted by `solve_deps`, all optional.
+///
+/// Every field is an `Option` with its default applied where it is used,
+/// since serde-wasm-bindgen reads absent keys as `undefined`,
+/// which only deserializes into an `Option`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SolveOptions {
+    /// Soft cap on the heap memory, in bytes.
+    /// When going over it, caches are evicted,
+    /// and the solve fails if that was not enough.
+    pub memory_limit: Option<usize>,
+    /// Only explore the N most prefered versions of each package at first.
+    /// If no solution is found that way, solve again with all versions.
+    pub max_versions_per_package: Option<usize>,
+    /// Static finite priorities of packages, higher priorities are decided first.
+    /// Packages not in there have a priority of 0.
+    pub package_priorities: Option<HashMap<String, f64>>,
+    /// JS function `(pkg) => number` giving the priority of a package,
+    /// overriding `package_priorities`.
+    #[serde(skip)]
+    pub package_priority: Option<js_sys::Function>,
+    /// JS function `(pkg, candidateVersions) => chosenVersion`
+    /// called when the solver must pick among several admissible versions.
+    #[serde(skip)]
+    pub choose_version: Option<js_sys::Function>,
+    /// JS function `(pkg, version) => boolean` telling if a package version
+    /// is available locally, without download.
+    #[serde(skip)]
+    pub has_locally: Option<js_sys::Function>,
+    /// Prefer versions available locally (according to `has_locally`),
+    /// and only pick versions requiring a download when necessary.
+    pub prefer_local: Option<bool>,
+    /// Try first the versions already recorded in the project elm.json,
+    /// to minimize changes.
+    pub prefer_project_versions: Option<bool>,
+    /// When solving with test dependencies, solve normal dependencies first and freeze them,
+    /// before solving test dependencies on top, like the elm compiler.
+    pub two_phase_test: Option<bool>,
+    /// Add a `licenses` field to the solution, grouping the resolved packages by license.
+    pub licenses: Option<bool>,
+    /// Add a `summary` field to the solution, with package counts,
+    /// maximum depth and elm version compatibility.
+    pub summary: Option<bool>,
+    /// Add a `freshness` field to the solution, with how far behind
+    /// the latest version each package is.
+    pub freshness: Option<bool>,
+    /// Release dates of package versions, used by `freshness`,
+    /// like `{ "elm/core": { "1.0.5": 1583342566 } }`.
+    pub release_dates: Option<HashMap<String, HashMap<String, f64>>>,
+    /// Add a `watchList` field to the solution, with the packages
+    /// whose future releases could change the solution.
+    pub watch_list: Option<bool>,
+    /// Add a `downloads` field to the solution, with the URLs of the artifacts to download.
+    pub downloads: Option<bool>,
+    /// Address of the package registry used for `downloads`,
+    /// `https://package.elm-lang.org` by default.
+    pub registry_url: Option<String>,
+    /// JS function `(pkg, version) => { url, hash }` returning the `endpoint.json`
+    /// of a package version, added to `downloads`.
+    #[serde(skip)]
+    pub fetch_endpoint: Option<js_sys::Function>,
+    /// `AbortSignal` cancelling the solve when aborted.
+    #[serde(skip)]
+    pub signal: Option<JsValue>,
+    /// Opaque value passed as last argument to every callback.
+    #[serde(skip)]
+    pub context: Option<JsValue>,
+    /// Value of `this` when calling the callbacks.
+    #[serde(skip)]
+    pub this_arg: Option<JsValue>,
+    /// Add an `install` field to the solution, with the packages missing locally
+    /// (according to `has_locally`), in dependency order.
+    pub install_plan: Option<bool>,
+    /// Add a `graph` field to the solution, with the dependencies of each package.
+    pub graph: Option<bool>,
+    /// Add a `provenance` field to the solution, with where the elm.json
+    /// and the artifact of each package come from.
+    pub provenance: Option<bool>,
+    /// Add an `elmCompatibility` field to the solution, with the intersection
+    /// of the `elm-version` ranges of all packages.
+    pub elm_compatibility: Option<bool>,
+    /// Packages forced to an exact version throughout the solve.
+    pub pins: Option<HashMap<String, String>>,
+    /// Packages replaced by a fixed version from another origin (git, vendored, fork).
+    pub overrides: Option<HashMap<String, Override>>,
+    /// Format of the returned JSON strings.
+    pub output: Option<OutputFormat>,
+    /// JS function `(requests: Array<[pkg, version]>) => string[]` fetching
+    /// several elm.json at once, used instead of `fetch_elm_json`.
+    #[serde(skip)]
+    pub fetch_elm_json_batch: Option<js_sys::Function>,
+    /// JS function `(pkgs: string[]) => Record<string, string[]>` listing the versions
+    /// of several packages at once, used instead of `list_available_versions`.
+    #[serde(skip)]
+    pub list_available_versions_batch: Option<js_sys::Function>,
+    /// Maximum number of elm.json requested per call of `fetch_elm_json_batch`.
+    pub batch_size: Option<usize>,
+    /// Maximum size in bytes of an elm.json returned by the callbacks, 1 MiB by default.
+    pub max_document_size: Option<usize>,
+    /// Maximum number of retries of `fetch_elm_json` and `list_available_versions`
+    /// when they fail transiently.
+    pub retries: Option<u32>,
+    /// Throw an `Error` with the derivation tree in addition to the text report
+    /// when there is no solution.
+    pub error_tree: Option<bool>,
+    /// Maximum number of lines of the report when there is no solution.
+    pub max_report_lines: Option<usize>,
+    /// Format of the report when there is no solution.
+    pub report_format: Option<ReportFormat>,
+    /// Suggest the closest versions of the project dependencies making a solution possible
+    /// when there is none.
+    pub suggest_versions: Option<bool>,
+    /// Built-in objective of the search for the best solution.
+    pub objective: Option<Objective>,
+    /// JS function `(solution) => number` giving the score of a solution, higher is better,
+    /// overriding `objective`.
+    #[serde(skip)]
+    pub score: Option<js_sys::Function>,
+    /// Maximum number of additional solves when searching for the best solution.
+    pub budget: Option<u32>,
+    /// Seed randomizing the tie-breaking of decisions, to sample other valid solutions.
+    pub seed: Option<u32>,
+    /// Version of the Elm compiler targeted, excluding the package versions
+    /// whose `elm-version` range does not contain it.
+    #[serde(deserialize_with = "deserialize_version")]
+    pub elm_version: Option<SemVer>,
+    /// Only licenses allowed in the solution, any license if empty.
+    pub allowed_licenses: Option<Vec<String>>,
+    /// Licenses never allowed in the solution.
+    pub denied_licenses: Option<Vec<String>>,
+    /// What to do when the solution has packages with a license not allowed.
+    pub license_policy: Option<LicensePolicy>,
+    /// Security advisories on package versions.
+    pub advisories: Option<Vec<Advisory>>,
+    /// What to do with the package versions affected by the advisories.
+    pub advisory_policy: Option<AdvisoryPolicy>,
+    /// JS function `(pkg, version) => boolean` approving a package version
+    /// before the solver picks it.
+    #[serde(skip)]
+    pub approve: Option<js_sys::Function>,
+    /// Only authors whose packages may be part of the solution, any author if empty.
+    pub allowed_authors: Option<Vec<String>>,
+    /// Authors whose packages must never be part of the solution.
+    pub denied_authors: Option<Vec<String>>,
+    /// Log level for this call only, the global level is restored afterwards.
+    pub log_level: Option<LogLevel>,
+    /// Accept package names without author, for private registries.
+    pub relaxed_package_names: Option<bool>,
+}
+
+/// Options accepted by `init`, all optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InitOptions {
+    /// Level of the logs written to the JS console, `info` by default.
+    pub log_level: Option<LogLevel>,
+    /// Group the logs of each solve phase with `console.group`.
+    pub group_logs: Option<bool>,
+    /// Time each solve phase with `console.time`.
+    pub profile: Option<bool>,
+}
+
+impl InitOptions {
+    /// Read the options object, which can be `undefined` or `null`.
+    pub fn from_js(options: JsValue) -> Result<Self, JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok(Self::default());
+        }
+        Ok(serde_wasm_bindgen::from_value(options)?)
+    }
+}
+
+/// Log level, from the most silent to the most verbose.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn filter(self) -> log::LevelFilter {
+        match self {
+            Self::Off => log::LevelFilter::Off,
+            Self::Error => log::LevelFilter::Error,
+            Self::Warn => log::LevelFilter::Warn,
+            Self::Info => log::LevelFilter::Info,
+            Self::Debug => log::LevelFilter::Debug,
+            Self::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Format of the JSON strings returned by the solver.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// JSON on a single line.
+    #[default]
+    Compact,
+    /// JSON formatted like the elm compiler writes elm.json files,
+    /// so they can be diffed cleanly.
+    Pretty,
+    /// Native JS object instead of a JSON string.
+    Object,
+}
+
+impl OutputFormat {
+    /// Serialize a value to JSON, or to a JS object, in this format.
+    pub fn to_js<T: Serialize>(self, value: &T) -> Result<JsValue, JsValue> {
+        match self {
+            Self::Compact => Ok(JsValue::from_str(&serde_json::to_string(value).unwrap())),
+            Self::Pretty => Ok(JsValue::from_str(&crate::edit::to_pretty_json(value))),
+            Self::Object => {
+                let serializer =
+                    serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+                Ok(value.serialize(&serializer)?)
+            }
+        }
+    }
+}
+
+/// Objective of the search for the best solution.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Objective {
+    /// Fewest newer versions than the chosen ones, summed over all packages.
+    Recency,
+    /// Fewest packages in the solution.
+    FewestPackages,
+}
+
+/// Format of the report when there is no solution.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Report of pubgrub, as is.
+    #[default]
+    Text,
+    /// Report grouped in sections, one per conflict.
+    Sections,
+    /// Sections as markdown `<details>` elements.
+    Markdown,
+}
+
+/// Security advisory on the versions of a package.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Advisory {
+    /// The affected package.
+    pub pkg: String,
+    /// Affected versions, with the syntax of additional constraints.
+    pub affected_range: String,
+    /// Severity of the vulnerability, like `"high"`.
+    pub severity: String,
+    /// Identifier of the advisory, like a CVE.
+    pub id: String,
+}
+
+/// What to do with the package versions affected by security advisories.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdvisoryPolicy {
+    /// Never pick affected versions.
+    #[default]
+    Avoid,
+    /// Pick affected versions like any other, and report them with the solution.
+    Report,
+}
+
+/// What to do when the solution has packages with a license not allowed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicensePolicy {
+    /// Fail with an error listing the packages.
+    #[default]
+    Fail,
+    /// Log a warning and list the packages with the solution.
+    Warn,
+}
+
+/// Package replaced by a fixed version from another origin than the registry.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Override {
+    /// Fixed version of the package.
+    pub version: String,
+    /// Where the package comes from, like a git URL or a vendored path.
+    pub origin: String,
+    /// The package elm.json, fetched with `fetch_elm_json` if not provided.
+    #[serde(default)]
+    pub elm_json: Option<String>,
+}
+
+impl SolveOptions {
+    /// Read the options object, which can be `undefined` or `null`.
+    pub fn from_js(options: JsValue) -> Result<Self, JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok(Self::default());
+        }
+        let mut solve_options: Self = serde_wasm_bindgen::from_value(options.clone())?;
+        solve_options.package_priority = get_function(&options, "packagePriority")?;
+        solve_options.choose_version = get_function(&options, "chooseVersion")?;
+        solve_options.has_locally = get_function(&options, "hasLocally")?;
+        solve_options.fetch_endpoint = get_function(&options, "fetchEndpoint")?;
+        solve_options.score = get_function(&options, "score")?;
+        solve_options.approve = get_function(&options, "approve")?;
+        solve_options.fetch_elm_json_batch = get_function(&options, "fetchElmJsonBatch")?;
+        solve_options.list_available_versions_batch =
+            get_function(&options, "listAvailableVersionsBatch")?;
+        solve_options.signal = get_value(&options, "signal")?;
+        solve_options.context = get_value(&options, "context")?;
+        solve_options.this_arg = get_value(&options, "thisArg")?;
+        solve_options.check()?;
+        Ok(solve_options)
+    }
+
+    /// Read the options of a non-JS host, given as JSON.
+    ///
+    /// Options taking JS values, like the callbacks or the `AbortSignal`, are not available.
+    pub fn from_json(options: serde_json::Value) -> Result<Self, String> {
+        let mut solve_options: Self =
+            serde_json::from_value(options).map_err(|err| format!("Invalid options: {}", err))?;
+        solve_options.check()?;
+        Ok(solve_options)
+    }
+
+    /// Check the options once all of them are read, and give an empty author
+    /// to package names without author if they are relaxed.
+    fn check(&mut self) -> Result<(), String> {
+        for (pkg, priority) in self.package_priorities.iter().flatten() {
+            if !priority.is_finite() {
+                return Err(format!("The priority of {} must be a finite number", pkg));
+            }
+        }
+        if self.relaxed_package_names == Some(true) {
+            self.qualify_package_names();
+        }
+        if self.prefer_local == Some(true) && self.has_locally.is_none() {
+            return Err("The option `preferLocal` requires the `hasLocally` function".to_string());
+        }
+        if self.install_plan == Some(true) && self.has_locally.is_none() {
+            return Err("The option `installPlan` requires the `hasLocally` function".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl SolveOptions {
+    /// Give an empty author to the package names without author of the options,
+    /// like the solver does for the `relaxed_package_names` option.
+    fn qualify_package_names(&mut self) {
+        fn qualify_keys<T>(map: &mut Option<HashMap<String, T>>) {
+            if let Some(map) = map {
+                *map = (std::mem::take(map).into_iter())
+                    .map(|(pkg, value)| (names::qualify(&pkg), value))
+                    .collect();
+            }
+        }
+        qualify_keys(&mut self.package_priorities);
+        qualify_keys(&mut self.release_dates);
+        qualify_keys(&mut self.pins);
+        qualify_keys(&mut self.overrides);
+        for override_ in self.overrides.iter_mut().flat_map(|o| o.values_mut()) {
+            if let Some(elm_json) = &mut override_.elm_json {
+                if let Ok(qualified) = names::qualify_elm_json(elm_json) {
+                    *elm_json = qualified;
+                }
+            }
+        }
+        for advisory in self.advisories.iter_mut().flatten() {
+            advisory.pkg = names::qualify(&advisory.pkg);
+        }
+    }
+
+    /// Version of the Elm compiler targeted, the `elm_version` option or elm 0.19.1 by default.
+    pub fn target_elm_version(&self) -> SemVer {
+        (self.elm_version).unwrap_or_else(|| SemVer::from(crate::formats::ELM_TEST_RS_ELM_VERSION))
+    }
+
+    /// Use the methods of a provider object as callbacks, called with it as `this`.
+    ///
+    /// Return its `fetchElmJson` and `listAvailableVersions` methods.
+    /// Its other methods are used as callbacks missing from the options.
+    pub fn use_provider(
+        &mut self,
+        provider: JsValue,
+    ) -> Result<(js_sys::Function, js_sys::Function), JsValue> {
+        let required = |field: &str| {
+            get_function(&provider, field)?
+                .ok_or_else(|| JsValue::from(format!("The provider has no `{}` method", field)))
+        };
+        let js_fetch_elm_json = required("fetchElmJson")?;
+        let js_list_available_versions = required("listAvailableVersions")?;
+        if self.package_priority.is_none() {
+            self.package_priority = get_function(&provider, "packagePriority")?;
+        }
+        if self.choose_version.is_none() {
+            self.choose_version = get_function(&provider, "chooseVersion")?;
+        }
+        if self.has_locally.is_none() {
+            self.has_locally = get_function(&provider, "hasLocally")?;
+        }
+        if self.fetch_endpoint.is_none() {
+            self.fetch_endpoint = get_function(&provider, "fetchEndpoint")?;
+        }
+        if self.approve.is_none() {
+            self.approve = get_function(&provider, "approve")?;
+        }
+        if self.this_arg.is_none() {
+            self.this_arg = Some(provider);
+        }
+        Ok((js_fetch_elm_json, js_list_available_versions))
+    }
+}
+
+/// Retrieve an optional field from a JS object, absent if `undefined` or `null`.
+fn get_value(object: &JsValue, field: &str) -> Result<Option<JsValue>, JsValue> {
+    let value = js_sys::Reflect::get(object, &JsValue::from_str(field))?;
+    Ok(Some(value).filter(|v| !(v.is_undefined() || v.is_null())))
+}
+
+/// Retrieve an optional function field from a JS object.
+fn get_function(object: &JsValue, field: &str) -> Result<Option<js_sys::Function>, JsValue> {
+    let value = js_sys::Reflect::get(object, &JsValue::from_str(field))?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    match value.dyn_into() {
+        Ok(function) => Ok(Some(function)),
+        Err(_) => Err(format!("The option `{}` must be a function", field).into()),
+    }
+}
+
+/// Deserialize an optional version written like `"0.19.1"`.
+fn deserialize_version<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<SemVer>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(version) => (SemVer::from_str(&version).map(Some)).map_err(|err| {
+            serde::de::Error::custom(format!("Invalid version {}: {}", version, err))
+        }),
+        None => Ok(None),
+    }
+}
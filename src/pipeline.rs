@@ -0,0 +1,464 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Solves configured by the options, free of JS values,
+//! shared by the JS API and the `wasi` protocol.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::report::{DefaultStringReporter, DerivationTree, Reporter};
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+
+use crate::provider::{self, JsProvider};
+use crate::{advisory, constraints, install, metadata, names, optimize, options, reach};
+use crate::{report, solver, spans, stats};
+
+/// Read the project elm.json and additional constraints of a solve.
+///
+/// With the `relaxedPackageNames` option, package names without author
+/// are given an empty author.
+pub fn read_solve_input(
+    project_elm_json_str: &str,
+    additional_constraints: HashMap<String, constraints::AdditionalConstraint>,
+    options: &options::SolveOptions,
+) -> anyhow::Result<(ProjectConfig, Vec<(Pkg, Constraint)>)> {
+    let (project_elm_json_str, additional_constraints) =
+        if options.relaxed_package_names.unwrap_or(false) {
+            let qualified = (additional_constraints.into_iter())
+                .map(|(pkg, constraint)| (names::qualify(&pkg), constraint))
+                .collect();
+            let project_elm_json_str = names::qualify_elm_json(project_elm_json_str)
+                .context("Failed to decode the elm.json")?;
+            (Cow::Owned(project_elm_json_str), qualified)
+        } else {
+            (Cow::Borrowed(project_elm_json_str), additional_constraints)
+        };
+    let project_elm_json =
+        serde_json::from_str(&project_elm_json_str).context("Failed to decode the elm.json")?;
+    Ok((
+        project_elm_json,
+        parse_additional_constraints(additional_constraints)?,
+    ))
+}
+
+/// Parse the `pins` option, mapping packages to their pinned version.
+fn parse_pins(options: &options::SolveOptions) -> anyhow::Result<HashMap<Pkg, SemVer>> {
+    (options.pins.iter().flatten())
+        .map(|(pkg, version)| Ok((Pkg::from_str(pkg)?, SemVer::from_str(version)?)))
+        .collect::<anyhow::Result<_>>()
+        .context("Failed to decode the pins")
+}
+
+/// Parse the `advisories` option.
+fn parse_advisories(options: &options::SolveOptions) -> anyhow::Result<Vec<advisory::Advisory>> {
+    advisory::parse(options.advisories.as_deref().unwrap_or_default())
+        .context("Failed to decode the advisories")
+}
+
+/// Advisories whose affected versions must never be picked, according to the policy.
+fn avoided_advisories(options: &options::SolveOptions) -> anyhow::Result<Vec<advisory::Advisory>> {
+    match options.advisory_policy.unwrap_or_default() {
+        options::AdvisoryPolicy::Avoid => parse_advisories(options),
+        options::AdvisoryPolicy::Report => Ok(Vec::new()),
+    }
+}
+
+/// Parse the `overrides` option, mapping packages to their version and elm.json.
+fn parse_overrides(
+    options: &options::SolveOptions,
+) -> anyhow::Result<HashMap<Pkg, (SemVer, Option<String>)>> {
+    (options.overrides.iter().flatten())
+        .map(|(pkg, o)| {
+            let version = SemVer::from_str(&o.version)?;
+            Ok((Pkg::from_str(pkg)?, (version, o.elm_json.clone())))
+        })
+        .collect::<anyhow::Result<_>>()
+        .context("Failed to decode the overrides")
+}
+
+/// Dedicated error when pinned versions take part in a failed resolution.
+pub fn pins_error(
+    tree: &DerivationTree<Pkg, SemVer>,
+    pins: &HashMap<Pkg, SemVer>,
+    options: &options::SolveOptions,
+) -> Option<anyhow::Error> {
+    let mut packages = Vec::new();
+    solver::packages_in_tree(tree, &mut packages);
+    let mut involved: Vec<String> = (pins.iter())
+        .filter(|(pkg, _)| packages.contains(pkg))
+        .map(|(pkg, version)| format!("{}@{}", pkg, version))
+        .collect();
+    if involved.is_empty() {
+        return None;
+    }
+    involved.sort();
+    Some(
+        anyhow::anyhow!(report::text_report(tree, options)).context(format!(
+            "The pinned versions {} make the resolution impossible",
+            involved.join(", ")
+        )),
+    )
+}
+
+/// Try first the versions of the project elm.json with the `preferProjectVersions` option.
+pub fn prefer_project_versions(
+    provider: JsProvider,
+    options: &options::SolveOptions,
+    project_elm_json: &ProjectConfig,
+) -> JsProvider {
+    if options.prefer_project_versions.unwrap_or(false) {
+        provider.with_prefered_versions(project_versions(project_elm_json))
+    } else {
+        provider
+    }
+}
+
+/// Configure the provider of a solve with the pins, overrides and advisories of the options.
+pub fn configure_provider(
+    provider: JsProvider,
+    options: &options::SolveOptions,
+    project_elm_json: &ProjectConfig,
+) -> anyhow::Result<JsProvider> {
+    Ok(prefer_project_versions(provider, options, project_elm_json)
+        .with_pins(parse_pins(options)?)
+        .with_overrides(parse_overrides(options)?)
+        .with_advisories(&avoided_advisories(options)?))
+}
+
+/// Solve the dependencies of a project, like `solve_deps`, with a provider configured
+/// by `configure_provider`, and search for the best solution according to the options.
+pub fn solve_with_options(
+    provider: &JsProvider,
+    options: &options::SolveOptions,
+    (project_elm_json, use_test, additional_constraints): SolveInput,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    let solve = || {
+        solver::solve_deps_js(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            provider,
+            options.two_phase_test.unwrap_or(false),
+        )
+    };
+    tracked_solve(provider, || {
+        let solution = solve()?;
+        optimize_solution(
+            solution,
+            options,
+            provider,
+            project_elm_json,
+            use_test,
+            solve,
+        )
+    })
+}
+
+/// Run a solve, recording its statistics.
+pub fn tracked_solve<T>(provider: &JsProvider, solve: impl FnOnce() -> T) -> T {
+    stats::start_solve();
+    let start = stats::now_ms();
+    let result = solve();
+    let total_ms = stats::now_ms() - start;
+    stats::end_solve(provider.cache_bytes(), provider.call_stats());
+    stats::record_perf(total_ms, provider.perf(), provider.call_stats());
+    result
+}
+
+/// Search for the best solution according to the `objective` or `score` options, if any.
+fn optimize_solution(
+    solution: AppDependencies,
+    options: &options::SolveOptions,
+    provider: &JsProvider,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    solve: impl Fn() -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    if options.objective.is_none() && options.score.is_none() {
+        return Ok(solution);
+    }
+    let _span = spans::Span::enter("optimization", &[]);
+    // The direct dependencies of an application are fixed by its elm.json.
+    let fixed: Vec<Pkg> = match project_elm_json {
+        ProjectConfig::Application(_) => {
+            solver::direct_dependencies(project_elm_json, use_test, &[])
+                .into_keys()
+                .collect()
+        }
+        ProjectConfig::Package(_) => Vec::new(),
+    };
+    let solve_excluding = |excluded: &[(Pkg, SemVer)]| {
+        provider.set_excluded(excluded.to_vec());
+        let result = solve();
+        provider.set_excluded(Vec::new());
+        result
+    };
+    let score = |solution: &AppDependencies| -> Result<f64, Box<dyn std::error::Error>> {
+        match (&options.score, options.objective) {
+            (Some(js_score), _) => {
+                let serializer =
+                    serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+                let js_solution = serde::Serialize::serialize(solution, &serializer)?;
+                let js_value = js_score
+                    .call1(&JsValue::NULL, &js_solution)
+                    .map_err(|js_err| format!("{:?}", js_err))?;
+                Ok(js_value.as_f64().context("Not a number?")?)
+            }
+            (None, Some(objective)) => optimize::objective_score(objective, solution, provider),
+            (None, None) => Ok(0.0),
+        }
+    };
+    let budget = options.budget.unwrap_or(optimize::DEFAULT_BUDGET);
+    optimize::improve(solution, &fixed, budget, solve_excluding, score)
+}
+
+/// Project, `use_test` and additional constraints of a solve.
+pub type SolveInput<'a> = (&'a ProjectConfig, bool, &'a [(Pkg, Constraint)]);
+
+/// Explain why a solve failed, with a dedicated error when pinned versions take part in it,
+/// or the report in the format of the options when there is no solution.
+pub fn solve_error(
+    err: PubGrubError<Pkg, SemVer>,
+    provider: &JsProvider,
+    options: &options::SolveOptions,
+    solve: SolveInput,
+) -> anyhow::Error {
+    match err {
+        PubGrubError::NoSolution(tree) => pins_error(&tree, provider.pins(), options)
+            .unwrap_or_else(|| {
+                let hints = version_hints(&tree, provider, options, solve);
+                anyhow::anyhow!(report::text_report_with_hints(&tree, &hints, options))
+            }),
+        err => handle_pubgrub_error(err),
+    }
+}
+
+/// Closest versions of the project dependencies making a solution possible,
+/// with the `suggestVersions` option.
+pub fn version_hints(
+    tree: &DerivationTree<Pkg, SemVer>,
+    provider: &JsProvider,
+    options: &options::SolveOptions,
+    (project_elm_json, use_test, additional_constraints): SolveInput,
+) -> Vec<report::VersionHint> {
+    if !options.suggest_versions.unwrap_or(false) {
+        return Vec::new();
+    }
+    let _span = spans::Span::enter("hints", &[]);
+    report::nearest_versions(
+        tree,
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        provider,
+    )
+}
+
+/// The solution as JSON, with the extra fields asked for in the options.
+pub fn solution_with_fields(
+    solution: &AppDependencies,
+    provider: &JsProvider,
+    options: &options::SolveOptions,
+    (project_elm_json, use_test, _): SolveInput,
+) -> anyhow::Result<serde_json::Value> {
+    let mut solution_json = serde_json::to_value(solution).unwrap();
+    let allowed_licenses = options.allowed_licenses.as_deref().unwrap_or_default();
+    let denied_licenses = options.denied_licenses.as_deref().unwrap_or_default();
+    if !(allowed_licenses.is_empty() && denied_licenses.is_empty()) {
+        let violations =
+            metadata::license_violations(solution, allowed_licenses, denied_licenses, provider)
+                .map_err(error_message)?;
+        match options.license_policy.unwrap_or_default() {
+            options::LicensePolicy::Fail if !violations.is_empty() => {
+                let lines: Vec<String> = violations.iter().map(|v| format!("  - {}", v)).collect();
+                anyhow::bail!(
+                    "License policy violation, the solution has packages with licenses not allowed:\n{}",
+                    lines.join("\n")
+                );
+            }
+            options::LicensePolicy::Fail => {}
+            options::LicensePolicy::Warn => {
+                for violation in &violations {
+                    log::warn!("License not allowed: {}", violation);
+                }
+                solution_json["licenseViolations"] = serde_json::to_value(violations).unwrap();
+            }
+        }
+    }
+    if options.licenses.unwrap_or(false) {
+        let licenses = metadata::licenses(solution, provider).map_err(error_message)?;
+        solution_json["licenses"] = serde_json::to_value(licenses).unwrap();
+    }
+    if options.summary.unwrap_or(false) {
+        let summary = metadata::summary(solution, provider).map_err(error_message)?;
+        solution_json["summary"] = serde_json::to_value(summary).unwrap();
+    }
+    if options.freshness.unwrap_or(false) {
+        let freshness = metadata::freshness(
+            solution,
+            provider,
+            options.release_dates.as_ref().unwrap_or(&HashMap::new()),
+        )
+        .map_err(error_message)?;
+        solution_json["freshness"] = serde_json::to_value(freshness).unwrap();
+    }
+    if options.watch_list.unwrap_or(false) {
+        let reach = reach::reachable_constraints(project_elm_json, use_test, provider)
+            .map_err(error_message)?;
+        let mut watch_list: Vec<&Pkg> = reach.keys().collect();
+        watch_list.sort();
+        solution_json["watchList"] = serde_json::to_value(watch_list).unwrap();
+    }
+    if options.downloads.unwrap_or(false) {
+        let registry_url =
+            (options.registry_url.as_deref()).unwrap_or(install::DEFAULT_REGISTRY_URL);
+        let downloads =
+            install::download_plan(solution, registry_url, provider).map_err(error_message)?;
+        solution_json["downloads"] = serde_json::to_value(downloads).unwrap();
+    }
+    if options.install_plan.unwrap_or(false) {
+        let install = install::install_plan(solution, provider).map_err(error_message)?;
+        solution_json["install"] = serde_json::to_value(install).unwrap();
+    }
+    if let Some(overrides) = options.overrides.as_ref().filter(|o| !o.is_empty()) {
+        let origins: BTreeMap<&Pkg, &str> = (solution.direct.keys())
+            .chain(solution.indirect.keys())
+            .filter_map(|pkg| Some((pkg, overrides.get(&pkg.to_string())?.origin.as_str())))
+            .collect();
+        solution_json["overrides"] = serde_json::to_value(origins).unwrap();
+    }
+    if options.graph.unwrap_or(false) {
+        let graph = metadata::graph(solution, provider).map_err(error_message)?;
+        solution_json["graph"] = serde_json::to_value(graph).unwrap();
+    }
+    if options.advisories.as_ref().is_some_and(|a| !a.is_empty()) {
+        let advisories = parse_advisories(options)?;
+        let vulnerabilities = advisory::vulnerabilities(solution, &advisories);
+        solution_json["vulnerabilities"] = serde_json::to_value(vulnerabilities).unwrap();
+    }
+    if options.provenance.unwrap_or(false) {
+        let registry_url =
+            (options.registry_url.as_deref()).unwrap_or(install::DEFAULT_REGISTRY_URL);
+        let provenance = install::provenance(
+            solution,
+            options.overrides.as_ref().unwrap_or(&HashMap::new()),
+            registry_url,
+            provider,
+        )
+        .map_err(error_message)?;
+        solution_json["provenance"] = serde_json::to_value(provenance).unwrap();
+    }
+    if options.elm_compatibility.unwrap_or(false) {
+        let compatibility = metadata::elm_compatibility(solution, project_elm_json, provider)
+            .map_err(error_message)?;
+        solution_json["elmCompatibility"] = serde_json::to_value(compatibility).unwrap();
+    }
+    if options.relaxed_package_names.unwrap_or(false) {
+        let qualified: HashSet<String> = (solution.direct.keys())
+            .chain(solution.indirect.keys())
+            .filter(|pkg| pkg.author.is_empty())
+            .map(|pkg| pkg.to_string())
+            .collect();
+        names::unqualify_json(&mut solution_json, &qualified);
+    }
+    Ok(solution_json)
+}
+
+/// Error only known by its message, like the boxed errors of the provider.
+fn error_message<E: std::fmt::Display>(err: E) -> anyhow::Error {
+    anyhow::anyhow!("{}", err)
+}
+
+/// Parse the additional constraints, provided as a map from package to constraints.
+pub fn parse_additional_constraints(
+    additional_constraints: HashMap<String, constraints::AdditionalConstraint>,
+) -> anyhow::Result<Vec<(Pkg, Constraint)>> {
+    additional_constraints
+        .into_iter()
+        .map(|(pkg, constraint)| Ok((Pkg::from_str(&pkg)?, constraint.parse()?)))
+        .collect()
+}
+
+pub fn handle_pubgrub_error(err: PubGrubError<Pkg, SemVer>) -> anyhow::Error {
+    if let Some(budget_err) = provider_error::<stats::MemoryBudgetExceeded>(&err) {
+        return anyhow::anyhow!("{}", budget_err);
+    }
+    if let Some(aborted) = provider_error::<provider::Aborted>(&err) {
+        return anyhow::anyhow!("{}", aborted);
+    }
+    if let Some(violation) = provider_error::<provider::PolicyViolation>(&err) {
+        return anyhow::anyhow!("{}", violation);
+    }
+    match err {
+        PubGrubError::NoSolution(tree) => {
+            anyhow::anyhow!(DefaultStringReporter::report(&tree))
+        }
+        PubGrubError::ErrorRetrievingDependencies {
+            package,
+            version,
+            source,
+        } => anyhow::anyhow!(
+            "An error occured while trying to retrieve dependencies of {}@{}:\n\n{}",
+            package,
+            version,
+            source
+        ),
+        PubGrubError::DependencyOnTheEmptySet {
+            package,
+            version,
+            dependent,
+        } => anyhow::anyhow!(
+            "{}@{} has an impossible dependency on {}",
+            package,
+            version,
+            dependent
+        ),
+        PubGrubError::SelfDependency { package, version } => {
+            anyhow::anyhow!("{}@{} somehow depends on itself", package, version)
+        }
+        PubGrubError::ErrorChoosingPackageVersion(err) => anyhow::anyhow!(
+            "There was an error while picking packages for dependency resolution:\n\n{}",
+            err
+        ),
+        PubGrubError::ErrorInShouldCancel(err) => {
+            anyhow::anyhow!("Dependency resolution was cancelled.\n\n{}", err)
+        }
+        PubGrubError::Failure(err) => anyhow::anyhow!(
+            "An unrecoverable error happened while solving dependencies:\n\n{}",
+            err
+        ),
+    }
+}
+
+/// All the package versions recorded in an application elm.json.
+fn project_versions(project_elm_json: &ProjectConfig) -> HashMap<Pkg, SemVer> {
+    match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            let deps = &app_config.dependencies;
+            let test_deps = &app_config.test_dependencies;
+            (deps.direct.iter())
+                .chain(deps.indirect.iter())
+                .chain(test_deps.direct.iter())
+                .chain(test_deps.indirect.iter())
+                .map(|(p, v)| (p.clone(), *v))
+                .collect()
+        }
+        ProjectConfig::Package(_) => HashMap::new(),
+    }
+}
+
+/// Find out if the solve was stopped by the dependency provider with a given error,
+/// like going over the memory limit, or being aborted.
+fn provider_error<E: std::error::Error + 'static>(err: &PubGrubError<Pkg, SemVer>) -> Option<&E> {
+    match err {
+        PubGrubError::ErrorRetrievingDependencies { source, .. } => source.downcast_ref(),
+        PubGrubError::ErrorChoosingPackageVersion(source) => source.downcast_ref(),
+        _ => None,
+    }
+}
@@ -0,0 +1,1468 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dependency provider calling the JS functions given to the solver,
+//! or the functions of a non-JS host.
+
+use std::borrow::Borrow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anyhow::Context;
+use elm_solve_deps::project_config::{PackageConfig, Pkg};
+use pubgrub::range::Range;
+use pubgrub::solver::{Dependencies, DependencyProvider};
+use pubgrub::version::SemanticVersion as SemVer;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::advisory::Advisory;
+use crate::install::Endpoint;
+use crate::names;
+use crate::options::SolveOptions;
+use crate::solver;
+use crate::spans::Span;
+use crate::stats::{self, CallStats, MemoryBudgetExceeded, SolvePerf};
+
+/// Wrapper around the two JS callbacks, shared across the whole solve.
+pub struct JsProvider {
+    source: Source,
+    interner: RefCell<Interner>,
+    /// Versions already listed, since pubgrub asks for them again at each decision.
+    versions_cache: RefCell<HashMap<Pkg, Vec<SemVer>>>,
+    /// Fetched elm.json documents, never evicted to guarantee a single fetch
+    /// and a single parse per package version.
+    elm_json_cache: RefCell<HashMap<(Pkg, SemVer), CachedElmJson>>,
+    /// The elm.json of overridden packages given in the options, parsed when first needed.
+    override_elm_jsons: HashMap<(Pkg, SemVer), String>,
+    call_stats: Cell<CallStats>,
+    perf: Cell<SolvePerf>,
+    memory_limit: Option<usize>,
+    /// Maximum number of versions listed per package, if any.
+    max_versions: Cell<Option<usize>>,
+    package_priorities: HashMap<String, f64>,
+    // js_package_priority(pkg: &str) -> f64;
+    js_package_priority: Option<js_sys::Function>,
+    /// Priorities already computed, to call the JS function only once per package.
+    priorities_cache: RefCell<HashMap<Pkg, f64>>,
+    // js_choose_version(pkg: &str, candidates: Vec<String>) -> Option<String>;
+    js_choose_version: Option<js_sys::Function>,
+    // js_has_locally(pkg: &str, version: &str) -> bool;
+    js_has_locally: Option<js_sys::Function>,
+    prefer_local: bool,
+    // js_fetch_endpoint(pkg: &str, version: &str) -> Endpoint;
+    js_fetch_endpoint: Option<js_sys::Function>,
+    /// Fetched endpoints, to call the JS function only once per package version.
+    endpoints_cache: RefCell<HashMap<(Pkg, SemVer), Endpoint>>,
+    /// Versions tried first for each package, if admissible.
+    prefered_versions: HashMap<Pkg, SemVer>,
+    /// Only versions allowed for each pinned package.
+    pins: HashMap<Pkg, SemVer>,
+    /// Fixed versions of overridden packages, never listed with the JS callback.
+    overrides: HashMap<Pkg, SemVer>,
+    /// `AbortSignal` cancelling the solve.
+    signal: Option<JsValue>,
+    /// Maximum number of retries of a callback failing transiently.
+    retries: u32,
+    // js_fetch_elm_json_batch(requests: Vec<(&str, &str)>) -> Vec<String>;
+    js_fetch_elm_json_batch: Option<js_sys::Function>,
+    batch_size: usize,
+    // js_list_available_versions_batch(pkgs: Vec<&str>) -> HashMap<String, Vec<String>>;
+    js_list_available_versions_batch: Option<js_sys::Function>,
+    /// Versions listed in a batch, before they are asked for.
+    prefetched_versions: RefCell<HashMap<Pkg, Vec<SemVer>>>,
+    /// Maximum size in bytes of a fetched elm.json.
+    max_document_size: usize,
+    /// Opaque value passed as last argument to every callback.
+    context: Option<JsValue>,
+    /// Value of `this` when calling the callbacks, `null` by default.
+    this_arg: JsValue,
+    /// First package version found depending on each package, to report call chains.
+    required_by: RefCell<HashMap<Pkg, (Pkg, SemVer)>>,
+    /// Package versions excluded from the solve, to search for other solutions.
+    excluded: RefCell<Vec<(Pkg, SemVer)>>,
+    /// Where the elm.json of package versions came from, when reported by the callbacks.
+    sources: RefCell<HashMap<(Pkg, SemVer), String>>,
+    /// Versions of packages affected by security advisories, never picked.
+    vulnerable: HashMap<Pkg, Range<SemVer>>,
+    /// Seed of the pseudo-random tie-breaking of decisions, if any.
+    seed: Option<u32>,
+    /// Version of the Elm compiler that package versions must be compatible with, if any.
+    elm_version: Option<SemVer>,
+    /// Only authors allowed in the solution, any author if empty.
+    allowed_authors: Vec<String>,
+    /// Authors never allowed in the solution.
+    denied_authors: Vec<String>,
+    // js_approve(pkg: &str, version: &str) -> bool;
+    js_approve: Option<js_sys::Function>,
+    /// Answers of the approval callback, to call it only once per package version.
+    approvals: RefCell<HashMap<(Pkg, SemVer), bool>>,
+}
+
+/// Function of a non-JS host returning the elm.json of a package version.
+pub type HostFetchElmJson = Box<dyn Fn(&str, SemVer) -> Result<String, Box<dyn Error>>>;
+
+/// Function of a non-JS host listing the versions of a package, in prefered order.
+pub type HostListAvailableVersions = Box<dyn Fn(&str) -> Result<Vec<SemVer>, Box<dyn Error>>>;
+
+/// Where the elm.json and versions of packages come from.
+enum Source {
+    Js {
+        // js_fetch_elm_json(pkg: &str, version: &str) -> String;
+        fetch_elm_json: js_sys::Function,
+        // js_list_available_versions(pkg: &str) -> Vec<String>;
+        list_available_versions: js_sys::Function,
+    },
+    /// Functions of a non-JS host, like the `wasi` protocol,
+    /// receiving the package names passed to the JS callbacks.
+    Host {
+        fetch_elm_json: HostFetchElmJson,
+        list_available_versions: HostListAvailableVersions,
+    },
+}
+
+/// Error when the solve is aborted with the `AbortSignal` of the options.
+#[derive(Debug)]
+pub struct Aborted {
+    /// Reason given to `abort()`, if any.
+    reason: Option<String>,
+}
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "The solve was aborted: {}", reason),
+            None => write!(f, "The solve was aborted"),
+        }
+    }
+}
+
+impl Error for Aborted {}
+
+/// Error when a JS callback returns an invalid elm.json.
+#[derive(Debug)]
+pub struct InvalidResponse {
+    pkg: Pkg,
+    version: SemVer,
+    reason: String,
+}
+
+impl InvalidResponse {
+    fn new(pkg: &Pkg, version: SemVer, reason: String) -> Self {
+        Self {
+            pkg: pkg.clone(),
+            version,
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for InvalidResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid elm.json returned for {} {}: {}",
+            self.pkg, self.version, self.reason
+        )
+    }
+}
+
+impl Error for InvalidResponse {}
+
+/// Error of a JS callback, with the chain of dependents leading to the package it was called for.
+#[derive(Debug)]
+pub struct CallbackError {
+    source: Box<dyn Error>,
+    pkg: Pkg,
+    version: Option<SemVer>,
+    /// Package versions depending on the package, from the closest one to the project.
+    chain: Vec<(Pkg, SemVer)>,
+}
+
+impl fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\n\nThis happened while retrieving {}",
+            self.source, self.pkg
+        )?;
+        if let Some(version) = &self.version {
+            write!(f, " {}", version)?;
+        }
+        writeln!(
+            f,
+            ", at depth {} of the dependencies:",
+            self.chain.len() + 1
+        )?;
+        for (dependent, version) in &self.chain {
+            writeln!(f, "  - required by {} {}", dependent, version)?;
+        }
+        write!(f, "  - required by the project")
+    }
+}
+
+impl Error for CallbackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Error when a package forbidden by the author policy is needed by the solution,
+/// with the chain of dependents pulling it in.
+#[derive(Debug)]
+pub struct PolicyViolation {
+    pkg: Pkg,
+    /// Package versions depending on the package, from the closest one to the project.
+    chain: Vec<(Pkg, SemVer)>,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Policy violation: packages of {} are not allowed, but {} is needed:",
+            self.pkg.author, self.pkg
+        )?;
+        for (dependent, version) in &self.chain {
+            writeln!(f, "  - required by {} {}", dependent, version)?;
+        }
+        write!(f, "  - required by the project")
+    }
+}
+
+impl Error for PolicyViolation {}
+
+/// Parsed elm.json in the cache.
+struct CachedElmJson {
+    config: Rc<PackageConfig>,
+    /// Size in bytes of its text, estimating the memory of the parsed config.
+    len: usize,
+}
+
+impl CachedElmJson {
+    fn new(config: &Rc<PackageConfig>, str_config: &str) -> Self {
+        Self {
+            config: Rc::clone(config),
+            len: str_config.len(),
+        }
+    }
+}
+
+/// Whether the `allowedAuthors` and `deniedAuthors` options allow an author.
+///
+/// Packages without author, with the `relaxedPackageNames` option,
+/// have the empty author `""`, which can be listed like any other.
+fn author_allowed(author: &str, allowed_authors: &[String], denied_authors: &[String]) -> bool {
+    let allowed = allowed_authors.is_empty() || allowed_authors.iter().any(|a| a == author);
+    allowed && !denied_authors.iter().any(|a| a == author)
+}
+
+/// Interning table of the JS strings passed to the callbacks.
+///
+/// Pubgrub asks many times for the same packages,
+/// so we only convert each package and version to a JS string once per solve.
+#[derive(Default)]
+struct Interner {
+    pkgs: HashMap<Pkg, JsValue>,
+    versions: HashMap<SemVer, JsValue>,
+    /// Remove the empty author of packages without author.
+    relaxed_names: bool,
+}
+
+impl Interner {
+    fn pkg(&mut self, pkg: &Pkg) -> JsValue {
+        if let Some(js_pkg) = self.pkgs.get(pkg) {
+            return js_pkg.clone();
+        }
+        let name = pkg.to_string();
+        let js_pkg = if self.relaxed_names {
+            JsValue::from_str(names::unqualify(&name))
+        } else {
+            JsValue::from_str(&name)
+        };
+        self.pkgs.insert(pkg.clone(), js_pkg.clone());
+        js_pkg
+    }
+
+    fn version(&mut self, version: SemVer) -> JsValue {
+        self.versions
+            .entry(version)
+            .or_insert_with(|| JsValue::from_str(&version.to_string()))
+            .clone()
+    }
+
+    fn size_bytes(&self) -> usize {
+        let pkgs_bytes: usize = self
+            .pkgs
+            .keys()
+            .map(|p| p.author.len() + p.pkg.len() + PKG_ENTRY_SIZE)
+            .sum();
+        pkgs_bytes + self.versions.len() * VERSION_ENTRY_SIZE
+    }
+}
+
+const PKG_ENTRY_SIZE: usize = std::mem::size_of::<(Pkg, JsValue)>();
+const VERSION_ENTRY_SIZE: usize = std::mem::size_of::<(SemVer, JsValue)>();
+const VERSION_SIZE: usize = std::mem::size_of::<SemVer>();
+const VERSIONS_ENTRY_SIZE: usize = std::mem::size_of::<(Pkg, Vec<SemVer>)>();
+const ELM_JSON_ENTRY_SIZE: usize = std::mem::size_of::<((Pkg, SemVer), CachedElmJson)>();
+
+/// Default maximum number of elm.json requested per batch.
+const DEFAULT_BATCH_SIZE: usize = 10;
+
+/// Default maximum size of a fetched elm.json, which is usually a few kilobytes.
+const DEFAULT_MAX_DOCUMENT_SIZE: usize = 1 << 20;
+
+impl JsProvider {
+    pub fn new(
+        js_fetch_elm_json: js_sys::Function,
+        js_list_available_versions: js_sys::Function,
+        options: &SolveOptions,
+    ) -> Self {
+        let source = Source::Js {
+            fetch_elm_json: js_fetch_elm_json,
+            list_available_versions: js_list_available_versions,
+        };
+        Self::with_source(source, options)
+    }
+
+    /// Provider calling the functions of a non-JS host instead of JS callbacks.
+    ///
+    /// It never touches JS values, as long as the options were read with
+    /// [SolveOptions::from_json], without callbacks.
+    pub fn with_host(
+        fetch_elm_json: HostFetchElmJson,
+        list_available_versions: HostListAvailableVersions,
+        options: &SolveOptions,
+    ) -> Self {
+        let source = Source::Host {
+            fetch_elm_json,
+            list_available_versions,
+        };
+        Self::with_source(source, options)
+    }
+
+    fn with_source(source: Source, options: &SolveOptions) -> Self {
+        Self {
+            source,
+            interner: RefCell::new(Interner {
+                relaxed_names: options.relaxed_package_names.unwrap_or(false),
+                ..Interner::default()
+            }),
+            versions_cache: RefCell::new(HashMap::new()),
+            elm_json_cache: RefCell::new(HashMap::new()),
+            override_elm_jsons: HashMap::new(),
+            call_stats: Cell::new(CallStats::new()),
+            perf: Cell::new(SolvePerf::default()),
+            memory_limit: options.memory_limit,
+            max_versions: Cell::new(options.max_versions_per_package),
+            package_priorities: options.package_priorities.clone().unwrap_or_default(),
+            js_package_priority: options.package_priority.clone(),
+            priorities_cache: RefCell::new(HashMap::new()),
+            js_choose_version: options.choose_version.clone(),
+            js_has_locally: options.has_locally.clone(),
+            prefer_local: options.prefer_local.unwrap_or(false),
+            js_fetch_endpoint: options.fetch_endpoint.clone(),
+            endpoints_cache: RefCell::new(HashMap::new()),
+            prefered_versions: HashMap::new(),
+            pins: HashMap::new(),
+            overrides: HashMap::new(),
+            signal: options.signal.clone(),
+            retries: options.retries.unwrap_or(0),
+            js_fetch_elm_json_batch: options.fetch_elm_json_batch.clone(),
+            batch_size: options.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+            js_list_available_versions_batch: options.list_available_versions_batch.clone(),
+            prefetched_versions: RefCell::new(HashMap::new()),
+            max_document_size: options
+                .max_document_size
+                .unwrap_or(DEFAULT_MAX_DOCUMENT_SIZE),
+            context: options.context.clone(),
+            this_arg: options.this_arg.clone().unwrap_or(JsValue::NULL),
+            required_by: RefCell::new(HashMap::new()),
+            excluded: RefCell::new(Vec::new()),
+            sources: RefCell::new(HashMap::new()),
+            vulnerable: HashMap::new(),
+            seed: options.seed,
+            elm_version: options.elm_version,
+            allowed_authors: options.allowed_authors.clone().unwrap_or_default(),
+            denied_authors: options.denied_authors.clone().unwrap_or_default(),
+            js_approve: options.approve.clone(),
+            approvals: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Fail if the `AbortSignal` of the options was aborted.
+    fn check_aborted(&self) -> Result<(), Aborted> {
+        let signal = match &self.signal {
+            Some(signal) => signal,
+            None => return Ok(()),
+        };
+        let get = |field: &str| js_sys::Reflect::get(signal, &JsValue::from_str(field));
+        if get("aborted").ok().and_then(|a| a.as_bool()) != Some(true) {
+            return Ok(());
+        }
+        let reason = get("reason").ok().filter(|r| !r.is_undefined());
+        Err(Aborted {
+            reason: reason.map(|r| match r.as_string() {
+                Some(reason) => reason,
+                None => String::from(stringify_js_error(&r)),
+            }),
+        })
+    }
+
+    /// Try first the given versions for their packages.
+    pub fn with_prefered_versions(mut self, prefered_versions: HashMap<Pkg, SemVer>) -> Self {
+        self.prefered_versions = prefered_versions;
+        self
+    }
+
+    /// Force the given packages to a single version throughout the solve.
+    pub fn with_pins(mut self, pins: HashMap<Pkg, SemVer>) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Packages forced to a single version with [JsProvider::with_pins].
+    pub fn pins(&self) -> &HashMap<Pkg, SemVer> {
+        &self.pins
+    }
+
+    /// Replace packages by a fixed version, with their elm.json if provided,
+    /// instead of the ones of the registry.
+    pub fn with_overrides(mut self, overrides: HashMap<Pkg, (SemVer, Option<String>)>) -> Self {
+        for (pkg, (version, elm_json)) in overrides {
+            if let Some(elm_json) = elm_json {
+                (self.override_elm_jsons).insert((pkg.clone(), version), elm_json);
+            }
+            self.overrides.insert(pkg, version);
+        }
+        self
+    }
+
+    /// Never pick the versions affected by the given advisories.
+    pub fn with_advisories(mut self, advisories: &[Advisory]) -> Self {
+        for advisory in advisories {
+            let range = (self.vulnerable)
+                .entry(advisory.pkg.clone())
+                .or_insert_with(Range::none);
+            *range = range.union(&advisory.affected_range);
+        }
+        self
+    }
+
+    /// Exclude the given package versions from the next solves.
+    pub fn set_excluded(&self, excluded: Vec<(Pkg, SemVer)>) {
+        *self.excluded.borrow_mut() = excluded;
+    }
+
+    fn is_excluded(&self, pkg: &Pkg, version: SemVer) -> bool {
+        (self.excluded.borrow().iter()).any(|(p, v)| p == pkg && *v == version)
+            || matches!(self.vulnerable.get(pkg), Some(range) if range.contains(&version))
+    }
+
+    /// Version a package is forced to, by a pin or an override.
+    fn forced_version(&self, pkg: &Pkg) -> Option<&SemVer> {
+        self.overrides.get(pkg).or_else(|| self.pins.get(pkg))
+    }
+
+    /// Check with the JS callback if a package version is available locally.
+    pub fn has_locally(&self, pkg: &Pkg, version: SemVer) -> Result<bool, Box<dyn Error>> {
+        let js_has_locally = match &self.js_has_locally {
+            Some(js_has_locally) => js_has_locally,
+            None => return Ok(false),
+        };
+        let mut interner = self.interner.borrow_mut();
+        let js_pkg = interner.pkg(pkg);
+        let js_version = interner.version(version);
+        drop(interner);
+        match self.timed(|| self.call(js_has_locally, &[&js_pkg, &js_version])) {
+            Ok(js_bool) => Ok(js_bool.as_bool().context("Not a boolean?")?),
+            Err(js_err) => Err(format!(
+                "An error occurred in the JS function call `has_locally({}, {})`.\n\n{}",
+                pkg,
+                version,
+                stringify_js_error(&js_err)
+            )
+            .into()),
+        }
+    }
+
+    /// Check with the JS callback, if provided, that a package version may be picked.
+    fn approve(&self, pkg: &Pkg, version: SemVer) -> Result<bool, Box<dyn Error>> {
+        let js_approve = match &self.js_approve {
+            Some(js_approve) => js_approve,
+            None => return Ok(true),
+        };
+        let key = (pkg.clone(), version);
+        if let Some(approved) = self.approvals.borrow().get(&key) {
+            return Ok(*approved);
+        }
+        let mut interner = self.interner.borrow_mut();
+        let js_pkg = interner.pkg(pkg);
+        let js_version = interner.version(version);
+        drop(interner);
+        let approved = match self.timed(|| self.call(js_approve, &[&js_pkg, &js_version])) {
+            Ok(js_bool) => js_bool.as_bool().context("Not a boolean?")?,
+            Err(js_err) => {
+                return Err(format!(
+                    "An error occurred in the JS function call `approve({}, {})`.\n\n{}",
+                    pkg,
+                    version,
+                    stringify_js_error(&js_err)
+                )
+                .into())
+            }
+        };
+        if !approved {
+            log::debug!("{} {} was not approved", pkg, version);
+        }
+        self.approvals.borrow_mut().insert(key, approved);
+        Ok(approved)
+    }
+
+    /// Fetch the `endpoint.json` of a package version with the JS callback, if provided.
+    ///
+    /// The JS callback is called at most once per package version.
+    pub fn fetch_endpoint(
+        &self,
+        pkg: &Pkg,
+        version: SemVer,
+    ) -> Result<Option<Endpoint>, Box<dyn Error>> {
+        let js_fetch_endpoint = match &self.js_fetch_endpoint {
+            Some(js_fetch_endpoint) => js_fetch_endpoint,
+            None => return Ok(None),
+        };
+        let key = (pkg.clone(), version);
+        if let Some(endpoint) = self.endpoints_cache.borrow().get(&key) {
+            return Ok(Some(endpoint.clone()));
+        }
+        let mut interner = self.interner.borrow_mut();
+        let js_pkg = interner.pkg(pkg);
+        let js_version = interner.version(version);
+        drop(interner);
+        match self.timed(|| self.call(js_fetch_endpoint, &[&js_pkg, &js_version])) {
+            Ok(js_endpoint) => {
+                let endpoint: Endpoint =
+                    serde_wasm_bindgen::from_value(js_endpoint).map_err(|e| e.to_string())?;
+                self.endpoints_cache
+                    .borrow_mut()
+                    .insert(key, endpoint.clone());
+                Ok(Some(endpoint))
+            }
+            Err(js_err) => Err(format!(
+                "An error occurred in the JS function call `fetch_endpoint({}, {})`.\n\n{}",
+                pkg,
+                version,
+                stringify_js_error(&js_err)
+            )
+            .into()),
+        }
+    }
+
+    /// Move versions available locally first, keeping the prefered order otherwise.
+    fn local_first(&self, pkg: &Pkg, versions: Vec<SemVer>) -> Result<Vec<SemVer>, Box<dyn Error>> {
+        let mut local = Vec::new();
+        let mut remote = Vec::new();
+        for v in versions {
+            if self.has_locally(pkg, v)? {
+                local.push(v);
+            } else {
+                remote.push(v);
+            }
+        }
+        local.extend(remote);
+        Ok(local)
+    }
+
+    fn has_priorities(&self) -> bool {
+        self.js_package_priority.is_some() || !self.package_priorities.is_empty()
+    }
+
+    /// Priority of a package, higher priorities are decided first.
+    fn priority(&self, pkg: &Pkg) -> Result<f64, Box<dyn Error>> {
+        if let Some(priority) = self.priorities_cache.borrow().get(pkg) {
+            return Ok(*priority);
+        }
+        let priority = match &self.js_package_priority {
+            Some(js_package_priority) => {
+                let js_pkg = self.interner.borrow_mut().pkg(pkg);
+                match self.timed(|| self.call(js_package_priority, &[&js_pkg])) {
+                    Ok(js_priority) => js_priority
+                        .as_f64()
+                        .filter(|priority| priority.is_finite())
+                        .context("Not a finite number?")?,
+                    Err(js_err) => {
+                        return Err(format!(
+                        "An error occurred in the JS function call `package_priority({})`.\n\n{}",
+                        pkg,
+                        stringify_js_error(&js_err)
+                    )
+                        .into())
+                    }
+                }
+            }
+            None => *self
+                .package_priorities
+                .get(&pkg.to_string())
+                .unwrap_or(&0.0),
+        };
+        self.priorities_cache
+            .borrow_mut()
+            .insert(pkg.clone(), priority);
+        Ok(priority)
+    }
+
+    /// Stop capping the number of versions listed per package.
+    /// Return `false` if there was no cap.
+    pub fn remove_versions_cap(&self) -> bool {
+        self.max_versions.take().is_some()
+    }
+
+    /// Check that we are still under the memory limit, if there is one.
+    /// Caches are evicted when over the limit, and if that is not enough, we give up.
+    /// The elm.json cache is never evicted since re-fetching may be expensive for the caller.
+    /// Allocations are not counted with the `minimal` feature, so there is no limit then.
+    fn check_memory(&self) -> Result<(), MemoryBudgetExceeded> {
+        let limit = match (self.memory_limit, stats::allocated_bytes()) {
+            (Some(limit), Some(allocated)) if allocated > limit => limit,
+            _ => return Ok(()),
+        };
+        let mut interner = self.interner.borrow_mut();
+        *interner = Interner {
+            relaxed_names: interner.relaxed_names,
+            ..Interner::default()
+        };
+        drop(interner);
+        self.versions_cache.borrow_mut().clear();
+        let allocated = stats::allocated_bytes().unwrap_or_default();
+        if allocated > limit {
+            Err(MemoryBudgetExceeded { allocated, limit })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Estimated size in bytes of the caches held by this provider.
+    pub fn cache_bytes(&self) -> usize {
+        let versions_bytes: usize = self
+            .versions_cache
+            .borrow()
+            .iter()
+            .map(|(p, vs)| {
+                p.author.len() + p.pkg.len() + VERSIONS_ENTRY_SIZE + vs.len() * VERSION_SIZE
+            })
+            .sum();
+        let elm_json_bytes: usize = self
+            .elm_json_cache
+            .borrow()
+            .iter()
+            .map(|((p, _), cached)| p.author.len() + p.pkg.len() + cached.len + ELM_JSON_ENTRY_SIZE)
+            .sum();
+        self.interner.borrow().size_bytes() + versions_bytes + elm_json_bytes
+    }
+
+    /// Statistics of the calls made to the JS callbacks.
+    pub fn call_stats(&self) -> CallStats {
+        self.call_stats.get()
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut CallStats)) {
+        let mut call_stats = self.call_stats.get();
+        f(&mut call_stats);
+        self.call_stats.set(call_stats);
+    }
+
+    /// Time spent in the JS callbacks, and bytes of elm.json parsed.
+    pub fn perf(&self) -> SolvePerf {
+        self.perf.get()
+    }
+
+    /// Call a JS callback with the given arguments,
+    /// followed by the `context` of the options if there is one,
+    /// and the `thisArg` of the options as `this`.
+    fn call(&self, function: &js_sys::Function, args: &[&JsValue]) -> Result<JsValue, JsValue> {
+        let this = &self.this_arg;
+        match (&self.context, args) {
+            (None, [arg]) => function.call1(this, arg),
+            (None, [arg1, arg2]) => function.call2(this, arg1, arg2),
+            (context, _) => {
+                let js_args: js_sys::Array = args.iter().cloned().chain(context).collect();
+                function.apply(this, &js_args)
+            }
+        }
+    }
+
+    /// Call a JS callback, measuring the time spent in it.
+    fn timed<R>(&self, call: impl FnOnce() -> R) -> R {
+        let start = stats::now_ms();
+        let result = call();
+        let mut perf = self.perf.get();
+        perf.callback_ms += stats::now_ms() - start;
+        self.perf.set(perf);
+        result
+    }
+
+    /// Call a JS callback, retrying up to the `retries` option when it fails transiently.
+    ///
+    /// A transient failure is either an exception with a `retryable` field set to `true`,
+    /// or a returned object `{ retryAfterMs }`.
+    /// Retries wait `retryAfterMs` if given, otherwise an exponential backoff from 100ms.
+    fn with_retries(
+        &self,
+        call: impl Fn() -> Result<JsValue, JsValue>,
+    ) -> Result<JsValue, JsValue> {
+        let mut attempt = 0;
+        loop {
+            let result = self.timed(&call);
+            let (retry, retry_after) = match &result {
+                Ok(value) => (retry_after_ms(value).is_some(), retry_after_ms(value)),
+                Err(js_err) => {
+                    let retryable = js_sys::Reflect::get(js_err, &JsValue::from_str("retryable"));
+                    let retryable = retryable.ok().and_then(|r| r.as_bool()) == Some(true);
+                    (retryable, retry_after_ms(js_err))
+                }
+            };
+            if !retry {
+                return result;
+            }
+            if attempt >= self.retries {
+                return match result {
+                    Ok(_) => Err(JsValue::from_str(&format!(
+                        "Still asked to retry after {} retries",
+                        attempt
+                    ))),
+                    err => err,
+                };
+            }
+            let backoff = 100.0 * 2_f64.powi(attempt as i32);
+            log::warn!("Transient failure of a JS callback, retrying");
+            sleep_ms(retry_after.unwrap_or(backoff));
+            attempt += 1;
+        }
+    }
+
+    /// Name of a package passed to the functions of a non-JS host,
+    /// the same than the one passed to the JS callbacks.
+    fn host_name(&self, pkg: &Pkg) -> String {
+        let name = pkg.to_string();
+        if self.interner.borrow().relaxed_names {
+            return names::unqualify(&name).to_string();
+        }
+        name
+    }
+
+    /// Parse an elm.json, counting its bytes.
+    fn parse_elm_json(&self, str_config: &str) -> serde_json::Result<PackageConfig> {
+        let mut perf = self.perf.get();
+        perf.bytes_parsed += str_config.len();
+        self.perf.set(perf);
+        if self.interner.borrow().relaxed_names {
+            return serde_json::from_str(&names::qualify_elm_json(str_config)?);
+        }
+        serde_json::from_str(str_config)
+    }
+
+    /// Retrieve the elm.json of a given package version.
+    ///
+    /// The JS callback is guaranteed to be called at most once per package version
+    /// during the solve, and each elm.json is parsed only once.
+    pub fn fetch_elm_json(
+        &self,
+        pkg: &Pkg,
+        version: SemVer,
+    ) -> Result<Rc<PackageConfig>, Box<dyn Error>> {
+        let key = (pkg.clone(), version);
+        if let Some(cached) = self.elm_json_cache.borrow().get(&key) {
+            self.update_stats(|s| s.fetch_elm_json_avoided += 1);
+            return Ok(Rc::clone(&cached.config));
+        }
+        if let Some(str_config) = self.override_elm_jsons.get(&key) {
+            self.update_stats(|s| s.fetch_elm_json_avoided += 1);
+            let config = Rc::new(self.parse_elm_json(str_config)?);
+            let cached = CachedElmJson::new(&config, str_config);
+            self.elm_json_cache.borrow_mut().insert(key, cached);
+            return Ok(config);
+        }
+        self.check_memory()?;
+        if let Some(js_batch) = &self.js_fetch_elm_json_batch {
+            let batch = self.next_batch(pkg, version);
+            let str_configs = (self.call_fetch_elm_json_batch(js_batch, pkg, &batch))
+                .map_err(|err| self.with_call_chain(err, pkg, Some(version)))?;
+            let mut config = None;
+            for (v, str_config) in batch.into_iter().zip(str_configs) {
+                // Only the requested version is checked strictly,
+                // invalid prefetched versions are fetched again if ever needed.
+                let checked = match self.check_elm_json(pkg, v, &str_config) {
+                    Ok(checked) => Rc::new(checked),
+                    Err(err) if v == version => return Err(err.into()),
+                    Err(err) => {
+                        log::warn!("Ignoring the prefetched elm.json of {}@{}: {}", pkg, v, err);
+                        continue;
+                    }
+                };
+                if v == version {
+                    config = Some(Rc::clone(&checked));
+                }
+                let cached = CachedElmJson::new(&checked, &str_config);
+                (self.elm_json_cache.borrow_mut()).insert((pkg.clone(), v), cached);
+            }
+            return Ok(config.expect("The requested version is always in its batch"));
+        }
+        let str_config = (self.call_fetch_elm_json(pkg, version))
+            .map_err(|err| self.with_call_chain(err, pkg, Some(version)))?;
+        let config = Rc::new(self.check_elm_json(pkg, version, &str_config)?);
+        let cached = CachedElmJson::new(&config, &str_config);
+        self.elm_json_cache.borrow_mut().insert(key, cached);
+        Ok(config)
+    }
+
+    /// Where the elm.json of a package version came from, if reported by the callback.
+    pub fn source(&self, pkg: &Pkg, version: SemVer) -> Option<String> {
+        self.sources.borrow().get(&(pkg.clone(), version)).cloned()
+    }
+
+    /// Unwrap an elm.json returned as `{ elmJson, source }`, recording its source.
+    fn record_source(&self, pkg: &Pkg, version: SemVer, js_config: JsValue) -> JsValue {
+        if !js_config.is_object() || js_config.is_instance_of::<js_sys::Uint8Array>() {
+            return js_config;
+        }
+        let get = |field: &str| js_sys::Reflect::get(&js_config, &JsValue::from_str(field));
+        match get("elmJson") {
+            Ok(elm_json) if !elm_json.is_undefined() => {
+                if let Some(source) = get("source").ok().and_then(|s| s.as_string()) {
+                    (self.sources.borrow_mut()).insert((pkg.clone(), version), source);
+                }
+                elm_json
+            }
+            _ => js_config,
+        }
+    }
+
+    /// Add to a callback error the chain of dependents leading to the package.
+    fn with_call_chain(
+        &self,
+        source: Box<dyn Error>,
+        pkg: &Pkg,
+        version: Option<SemVer>,
+    ) -> Box<dyn Error> {
+        Box::new(CallbackError {
+            source,
+            pkg: pkg.clone(),
+            version,
+            chain: self.call_chain(pkg),
+        })
+    }
+
+    /// Chain of dependents leading to a package, from the closest one to the project.
+    fn call_chain(&self, pkg: &Pkg) -> Vec<(Pkg, SemVer)> {
+        let required_by = self.required_by.borrow();
+        let mut chain: Vec<(Pkg, SemVer)> = Vec::new();
+        let mut current = pkg;
+        while let Some((dependent, version)) = required_by.get(current) {
+            if dependent == pkg || chain.iter().any(|(p, _)| p == dependent) {
+                break;
+            }
+            chain.push((dependent.clone(), *version));
+            current = dependent;
+        }
+        chain
+    }
+
+    /// Fail if the author of a package is forbidden by the `allowedAuthors`
+    /// or `deniedAuthors` options.
+    fn check_policy(&self, pkg: &Pkg) -> Result<(), PolicyViolation> {
+        if author_allowed(&pkg.author, &self.allowed_authors, &self.denied_authors) {
+            return Ok(());
+        }
+        Err(PolicyViolation {
+            pkg: pkg.clone(),
+            chain: self.call_chain(pkg),
+        })
+    }
+
+    /// Parse an elm.json returned by a JS callback,
+    /// and check that it is the one of the requested package version,
+    /// unless the package is overridden, since forks may keep their own name.
+    fn check_elm_json(
+        &self,
+        pkg: &Pkg,
+        version: SemVer,
+        str_config: &str,
+    ) -> Result<PackageConfig, InvalidResponse> {
+        let config = (self.parse_elm_json(str_config))
+            .map_err(|err| InvalidResponse::new(pkg, version, err.to_string()))?;
+        let overridden = self.overrides.contains_key(pkg);
+        if !overridden && (&config.name != pkg || config.version != version) {
+            let reason = format!("it is the elm.json of {} {}", config.name, config.version);
+            return Err(InvalidResponse::new(pkg, version, reason));
+        }
+        Ok(config)
+    }
+
+    /// Versions of a package fetched in the same batch as the given one.
+    ///
+    /// Those are the versions coming next in prefered order, not fetched yet,
+    /// since they are the ones the solver tries next if this one is rejected.
+    fn next_batch(&self, pkg: &Pkg, version: SemVer) -> Vec<SemVer> {
+        let mut batch = vec![version];
+        let versions_cache = self.versions_cache.borrow();
+        let elm_json_cache = self.elm_json_cache.borrow();
+        if let Some(versions) = versions_cache.get(pkg) {
+            let next = versions
+                .iter()
+                .skip_while(|v| **v != version)
+                .skip(1)
+                .filter(|v| !elm_json_cache.contains_key(&(pkg.clone(), **v)));
+            batch.extend(next.take(self.batch_size - 1));
+        }
+        batch
+    }
+
+    fn call_fetch_elm_json_batch(
+        &self,
+        js_batch: &js_sys::Function,
+        pkg: &Pkg,
+        versions: &[SemVer],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        self.update_stats(|s| s.fetch_elm_json_calls += 1);
+        let _span = Span::enter(
+            "fetch_elm_json_batch",
+            &[("pkg", pkg), ("count", &versions.len())],
+        );
+        let mut interner = self.interner.borrow_mut();
+        let js_pkg = interner.pkg(pkg);
+        let js_requests: js_sys::Array = versions
+            .iter()
+            .map(|v| js_sys::Array::of2(&js_pkg, &interner.version(*v)))
+            .collect();
+        drop(interner);
+        let str_versions = || {
+            let versions: Vec<_> = versions.iter().map(|v| v.to_string()).collect();
+            versions.join(", ")
+        };
+        match self.with_retries(|| self.call(js_batch, &[&js_requests])) {
+            Ok(js_configs) => {
+                let js_configs = js_sys::Array::from(&js_configs);
+                if js_configs.length() as usize != versions.len() {
+                    return Err(format!(
+                        "fetch_elm_json_batch returned {} elm.json for {} requests of {} ({})",
+                        js_configs.length(),
+                        versions.len(),
+                        pkg,
+                        str_versions()
+                    )
+                    .into());
+                }
+                let configs = (js_configs.iter().zip(versions))
+                    .map(|(js_config, version)| {
+                        let js_config = self.record_source(pkg, *version, js_config);
+                        elm_json_string(&js_config, self.max_document_size)
+                            .map_err(|reason| InvalidResponse::new(pkg, *version, reason))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(configs)
+            }
+            Err(js_err) => Err(format!(
+                "An error occurred in the JS function call `fetch_elm_json_batch` for {} ({}).\n\n{}",
+                pkg,
+                str_versions(),
+                stringify_js_error(&js_err)
+            )
+            .into()),
+        }
+    }
+
+    fn call_fetch_elm_json(&self, pkg: &Pkg, version: SemVer) -> Result<String, Box<dyn Error>> {
+        self.update_stats(|s| s.fetch_elm_json_calls += 1);
+        let _span = Span::enter("fetch_elm_json", &[("pkg", pkg), ("version", &version)]);
+        let js_fetch_elm_json = match &self.source {
+            Source::Js { fetch_elm_json, .. } => fetch_elm_json,
+            Source::Host { fetch_elm_json, .. } => {
+                let str_config = self.timed(|| fetch_elm_json(&self.host_name(pkg), version))?;
+                if str_config.len() > self.max_document_size {
+                    let reason = too_large(str_config.len(), self.max_document_size);
+                    return Err(InvalidResponse::new(pkg, version, reason).into());
+                }
+                return Ok(str_config);
+            }
+        };
+        let mut interner = self.interner.borrow_mut();
+        let js_pkg = interner.pkg(pkg);
+        let js_version = interner.version(version);
+        drop(interner);
+        match self.with_retries(|| self.call(js_fetch_elm_json, &[&js_pkg, &js_version])) {
+            Ok(js_config) => {
+                let js_config = self.record_source(pkg, version, js_config);
+                elm_json_string(&js_config, self.max_document_size)
+                    .map_err(|reason| InvalidResponse::new(pkg, version, reason).into())
+            }
+            Err(js_err) => Err(format!(
+                "An error occurred in the JS function call `fetch_elm_json({}, {})`.\n\n{}",
+                pkg,
+                version,
+                stringify_js_error(&js_err)
+            )
+            .into()),
+        }
+    }
+
+    /// List the available versions of a package, in prefered order.
+    ///
+    /// The JS callback is called at most once per package during the solve
+    /// (unless caches are evicted because of the memory limit).
+    /// Only the first versions are returned when the number of versions is capped.
+    pub fn list_available_versions(
+        &self,
+        pkg: &Pkg,
+    ) -> Result<std::vec::IntoIter<SemVer>, Box<dyn Error>> {
+        let cap = self.max_versions.get().unwrap_or(usize::MAX);
+        if let Some(versions) = self.versions_cache.borrow().get(pkg) {
+            self.update_stats(|s| s.list_available_versions_avoided += 1);
+            return Ok(versions
+                .iter()
+                .filter(|v| !self.is_excluded(pkg, **v))
+                .take(cap)
+                .cloned()
+                .collect::<Vec<_>>()
+                .into_iter());
+        }
+        self.check_memory()?;
+        let mut versions = match self.overrides.get(pkg) {
+            Some(version) => vec![*version],
+            None => (self.call_list_available_versions(pkg))
+                .map_err(|err| self.with_call_chain(err, pkg, None))?,
+        };
+        if let Some(pin) = self.pins.get(pkg) {
+            versions.retain(|v| v == pin);
+        }
+        if self.prefer_local {
+            versions = self.local_first(pkg, versions)?;
+        }
+        if let Some(prefered) = self.prefered_versions.get(pkg) {
+            if let Some(index) = versions.iter().position(|v| v == prefered) {
+                let prefered = versions.remove(index);
+                versions.insert(0, prefered);
+            }
+        }
+        self.versions_cache
+            .borrow_mut()
+            .insert(pkg.clone(), versions.clone());
+        versions.retain(|v| !self.is_excluded(pkg, *v));
+        versions.truncate(cap);
+        Ok(versions.into_iter())
+    }
+
+    fn call_list_available_versions(&self, pkg: &Pkg) -> Result<Vec<SemVer>, Box<dyn Error>> {
+        if self.js_list_available_versions_batch.is_some() {
+            self.prefetch_versions(&[pkg])?;
+            return (self.prefetched_versions.borrow_mut())
+                .remove(pkg)
+                .ok_or_else(|| {
+                    format!("list_available_versions_batch did not list {}", pkg).into()
+                });
+        }
+        self.update_stats(|s| s.list_available_versions_calls += 1);
+        let _span = Span::enter("list_available_versions", &[("pkg", pkg)]);
+        let js_list_available_versions = match &self.source {
+            Source::Js {
+                list_available_versions,
+                ..
+            } => list_available_versions,
+            Source::Host {
+                list_available_versions,
+                ..
+            } => return self.timed(|| list_available_versions(&self.host_name(pkg))),
+        };
+        let js_pkg = self.interner.borrow_mut().pkg(pkg);
+        match self.with_retries(|| self.call(js_list_available_versions, &[&js_pkg])) {
+            Ok(js_versions) => parse_versions(js_versions),
+            Err(js_err) => Err(format!(
+                "An error occurred in the JS function call `list_available_versions({})`.\n\n{}",
+                pkg,
+                stringify_js_error(&js_err)
+            )
+            .into()),
+        }
+    }
+}
+
+// Batched listing of versions.
+impl JsProvider {
+    /// List the versions of all the given packages not listed yet,
+    /// in a single call of the batched JS callback, if provided.
+    fn prefetch_versions(&self, pkgs: &[&Pkg]) -> Result<(), Box<dyn Error>> {
+        let js_batch = match &self.js_list_available_versions_batch {
+            Some(js_batch) => js_batch,
+            None => return Ok(()),
+        };
+        let versions_cache = self.versions_cache.borrow();
+        let prefetched = self.prefetched_versions.borrow();
+        let missing: Vec<&Pkg> = pkgs
+            .iter()
+            .cloned()
+            .filter(|p| !versions_cache.contains_key(p) && !prefetched.contains_key(p))
+            .filter(|p| !self.overrides.contains_key(p))
+            .collect();
+        drop(versions_cache);
+        drop(prefetched);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        self.update_stats(|s| s.list_available_versions_calls += 1);
+        let _span = Span::enter(
+            "list_available_versions_batch",
+            &[("count", &missing.len())],
+        );
+        let mut interner = self.interner.borrow_mut();
+        let js_pkgs: js_sys::Array = missing.iter().map(|p| interner.pkg(p)).collect();
+        drop(interner);
+        let str_pkgs = || {
+            let pkgs: Vec<_> = missing.iter().map(|p| p.to_string()).collect();
+            pkgs.join(", ")
+        };
+        let js_listing = self
+            .with_retries(|| self.call(js_batch, &[&js_pkgs]))
+            .map_err(|js_err| {
+                format!(
+                    "An error occurred in the JS function call `list_available_versions_batch` for {}.\n\n{}",
+                    str_pkgs(),
+                    stringify_js_error(&js_err)
+                )
+            })?;
+        let mut prefetched = self.prefetched_versions.borrow_mut();
+        for (pkg, js_pkg) in missing.iter().zip(js_pkgs.iter()) {
+            let js_versions = js_sys::Reflect::get(&js_listing, &js_pkg)
+                .map_err(|js_err| format!("Not an object? {}", stringify_js_error(&js_err)))?;
+            if !js_versions.is_undefined() {
+                prefetched.insert((*pkg).clone(), parse_versions(js_versions)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Version decision with the JS callback.
+impl JsProvider {
+    /// Let the JS callback pick a version among the candidates, in prefered order.
+    /// The callback is only called if there are multiple candidates,
+    /// and the first one is picked if it returns `null` or `undefined`.
+    fn choose_version(
+        &self,
+        js_choose_version: &js_sys::Function,
+        pkg: &Pkg,
+        candidates: &[SemVer],
+    ) -> Result<Option<SemVer>, Box<dyn Error>> {
+        if candidates.len() <= 1 {
+            return Ok(candidates.first().cloned());
+        }
+        let mut interner = self.interner.borrow_mut();
+        let js_pkg = interner.pkg(pkg);
+        let js_candidates: js_sys::Array =
+            candidates.iter().map(|v| interner.version(*v)).collect();
+        drop(interner);
+        let js_version = self
+            .timed(|| self.call(js_choose_version, &[&js_pkg, &js_candidates]))
+            .map_err(|js_err| {
+                format!(
+                    "An error occurred in the JS function call `choose_version({}, {:?})`.\n\n{}",
+                    pkg,
+                    candidates.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                    stringify_js_error(&js_err)
+                )
+            })?;
+        if js_version.is_undefined() || js_version.is_null() {
+            return Ok(candidates.first().cloned());
+        }
+        let str_version = js_version.as_string().context("Not a string?")?;
+        let version = SemVer::from_str(&str_version)?;
+        if candidates.contains(&version) {
+            Ok(Some(version))
+        } else {
+            Err(format!(
+                "choose_version({}, ...) returned {} which is not one of the candidate versions",
+                pkg, version
+            )
+            .into())
+        }
+    }
+}
+
+impl DependencyProvider<Pkg, SemVer> for JsProvider {
+    /// Pick the package with the fewest versions available in its range,
+    /// and choose the first version in prefered order.
+    ///
+    /// If packages have priorities, only those with the highest priority are considered.
+    /// Packages constrained to an exact version are picked first,
+    /// without even listing their available versions.
+    /// Remaining ties are broken by package name, so that decisions never depend on
+    /// the iteration order of the hash maps of the solver.
+    /// With a seed, ties are broken in a pseudo-random order derived from the seed instead,
+    /// and the version is picked pseudo-randomly among the candidates.
+    fn choose_package_version<T: Borrow<Pkg>, U: Borrow<Range<SemVer>>>(
+        &self,
+        potential_packages: impl Iterator<Item = (T, U)>,
+    ) -> Result<(T, Option<SemVer>), Box<dyn Error>> {
+        self.check_aborted()?;
+        let span = Span::enter("decision", &[]);
+        let (pkg, version) = self.decide(potential_packages)?;
+        span.record("pkg", pkg.borrow());
+        if let Some(version) = &version {
+            span.record("version", version);
+        }
+        Ok((pkg, version))
+    }
+
+    /// Load the dependencies from the elm.json retrieved with the JS callback.
+    fn get_dependencies(
+        &self,
+        package: &Pkg,
+        version: &SemVer,
+    ) -> Result<Dependencies<Pkg, SemVer>, Box<dyn Error>> {
+        self.check_aborted()?;
+        let pkg_config = self.fetch_elm_json(package, *version)?;
+        if let Some(elm_version) = &self.elm_version {
+            if !pkg_config.elm_version.0.contains(elm_version) {
+                log::debug!(
+                    "{} {} is not compatible with elm {}",
+                    package,
+                    version,
+                    elm_version
+                );
+                return Ok(Dependencies::Unknown);
+            }
+        }
+        let mut required_by = self.required_by.borrow_mut();
+        for dependency in pkg_config.dependencies.keys() {
+            (required_by.entry(dependency.clone())).or_insert_with(|| (package.clone(), *version));
+        }
+        drop(required_by);
+        Ok(Dependencies::Known(
+            (pkg_config.dependencies.iter())
+                .map(|(p, c)| (p.clone(), c.0.clone()))
+                .collect(),
+        ))
+    }
+}
+
+impl JsProvider {
+    /// Implementation of `choose_package_version`.
+    fn decide<T: Borrow<Pkg>, U: Borrow<Range<SemVer>>>(
+        &self,
+        potential_packages: impl Iterator<Item = (T, U)>,
+    ) -> Result<(T, Option<SemVer>), Box<dyn Error>> {
+        let mut potential_packages: Vec<(T, U)> = potential_packages.collect();
+        // All potential packages are needed, so a forbidden one is a policy violation.
+        for (pkg, _) in &potential_packages {
+            self.check_policy(pkg.borrow())?;
+        }
+        // Candidates come from a hash map, sort them to break ties deterministically.
+        match self.seed {
+            Some(seed) => potential_packages.sort_by_cached_key(|(p, _)| {
+                (
+                    seeded_rank(seed, &p.borrow().to_string()),
+                    p.borrow().clone(),
+                )
+            }),
+            None => potential_packages.sort_by(|(p1, _), (p2, _)| p1.borrow().cmp(p2.borrow())),
+        }
+        if self.has_priorities() {
+            let priorities = potential_packages
+                .iter()
+                .map(|(p, _)| self.priority(p.borrow()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let max_priority = priorities.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            potential_packages = potential_packages
+                .into_iter()
+                .zip(priorities)
+                .filter(|(_, priority)| *priority >= max_priority)
+                .map(|(p, _)| p)
+                .collect();
+        }
+        if let Some((index, version)) = potential_packages
+            .iter()
+            .enumerate()
+            .find_map(|(i, (_, range))| Some((i, solver::exact_version(range.borrow())?)))
+        {
+            let (pkg, _) = potential_packages.swap_remove(index);
+            if matches!(self.forced_version(pkg.borrow()), Some(forced) if forced != &version) {
+                return Ok((pkg, None));
+            }
+            if self.is_excluded(pkg.borrow(), version) || !self.approve(pkg.borrow(), version)? {
+                return Ok((pkg, None));
+            }
+            return Ok((pkg, Some(version)));
+        }
+        let pkgs: Vec<&Pkg> = potential_packages.iter().map(|(p, _)| p.borrow()).collect();
+        self.prefetch_versions(&pkgs)?;
+        // A failing callback stops the decision, instead of being called again later.
+        let mut counted = Vec::with_capacity(potential_packages.len());
+        for (p, range) in potential_packages {
+            let versions = self.list_available_versions(p.borrow())?;
+            let count = versions.filter(|v| range.borrow().contains(v)).count();
+            counted.push((count, p, range));
+        }
+        let (_, pkg, range) = counted
+            .into_iter()
+            .min_by_key(|(count, _, _)| *count)
+            .expect("potential_packages gave us an empty iterator");
+        let candidates = self
+            .list_available_versions(pkg.borrow())?
+            .filter(|v| range.borrow().contains(v));
+        // Approvals are checked lazily, only until a version is picked when possible.
+        let mut approved = candidates.filter_map(|v| match self.approve(pkg.borrow(), v) {
+            Ok(true) => Some(Ok(v)),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        });
+        let version = match &self.js_choose_version {
+            Some(js_choose_version) => {
+                let candidates: Vec<SemVer> = approved.collect::<Result<_, _>>()?;
+                self.choose_version(js_choose_version, pkg.borrow(), &candidates)?
+            }
+            None => match self.seed {
+                Some(seed) => {
+                    let candidates: Vec<SemVer> = approved.collect::<Result<_, _>>()?;
+                    (candidates.into_iter())
+                        .min_by_key(|v| seeded_rank(seed, &format!("{}@{}", pkg.borrow(), v)))
+                }
+                None => approved.next().transpose()?,
+            },
+        };
+        Ok((pkg, version))
+    }
+}
+
+/// Pseudo-random rank of a key for the given seed, the same on every machine.
+///
+/// This is the FNV-1a hash of the seed and key, mixed with the finalizer of SplitMix64.
+fn seeded_rank(seed: u32, key: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in seed.to_le_bytes().iter().chain(key.as_bytes()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Read the elm.json returned by a JS callback,
+/// either as a JSON string, as an already parsed object,
+/// or as raw UTF-8 bytes in a `Uint8Array` (like a node `Buffer`).
+///
+/// Documents over `max_size` bytes are rejected before being copied into the wasm memory.
+fn elm_json_string(js_config: &JsValue, max_size: usize) -> Result<String, String> {
+    let js_str = if let Some(bytes) = js_config.dyn_ref::<js_sys::Uint8Array>() {
+        if bytes.length() as usize > max_size {
+            return Err(too_large(bytes.length() as usize, max_size));
+        }
+        return String::from_utf8(bytes.to_vec()).map_err(|err| err.to_string());
+    } else if js_config.is_string() {
+        js_sys::JsString::from(js_config.clone())
+    } else if js_config.is_object() {
+        js_sys::JSON::stringify(js_config).map_err(|js_err| {
+            format!("Not serializable to JSON: {}", stringify_js_error(&js_err))
+        })?
+    } else {
+        return Err("Not a string nor an object?".to_string());
+    };
+    // A UTF-16 code unit is at least one byte in UTF-8.
+    if js_str.length() as usize > max_size {
+        return Err(too_large(js_str.length() as usize, max_size));
+    }
+    let str_config = String::from(js_str);
+    if str_config.len() > max_size {
+        return Err(too_large(str_config.len(), max_size));
+    }
+    Ok(str_config)
+}
+
+/// Reason for rejecting an elm.json of `size` bytes.
+fn too_large(size: usize, max_size: usize) -> String {
+    format!("{} bytes, over the limit of {} bytes", size, max_size)
+}
+
+/// Read the versions returned by a JS callback,
+/// either as an array of strings or as a string with one version per line.
+fn parse_versions(js_versions: JsValue) -> Result<Vec<SemVer>, Box<dyn Error>> {
+    let versions: Vec<String> = match js_versions.as_string() {
+        Some(lines) => lines
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+        None => serde_wasm_bindgen::from_value(js_versions)?,
+    };
+    let versions = versions
+        .iter()
+        .map(|v| SemVer::from_str(v))
+        .collect::<Result<_, _>>()?;
+    Ok(versions)
+}
+
+/// Read the `retryAfterMs` field of a JS object, if any.
+fn retry_after_ms(value: &JsValue) -> Option<f64> {
+    if !value.is_object() {
+        return None;
+    }
+    js_sys::Reflect::get(value, &JsValue::from_str("retryAfterMs"))
+        .ok()?
+        .as_f64()
+}
+
+/// Wait synchronously with `Atomics.wait`.
+/// Where blocking is not allowed, like the main thread of browsers, this does not wait.
+fn sleep_ms(ms: f64) {
+    let global = js_sys::global();
+    if !js_sys::Reflect::has(&global, &JsValue::from_str("SharedArrayBuffer")).unwrap_or(false) {
+        return;
+    }
+    let buffer = js_sys::SharedArrayBuffer::new(4);
+    let array = js_sys::Int32Array::new(&buffer);
+    let _ = js_sys::Atomics::wait_with_timeout(&array, 0, 0, ms);
+}
+
+fn stringify_js_error(js_err: &JsValue) -> js_sys::JsString {
+    js_sys::JSON::stringify(js_err).unwrap_or_else(|_| js_sys::JsString::from(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authors(authors: &[&str]) -> Vec<String> {
+        authors.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn any_author_allowed_by_default() {
+        assert!(author_allowed("elm", &[], &[]));
+        assert!(author_allowed("", &[], &[]));
+    }
+
+    #[test]
+    fn only_allowed_authors() {
+        let allowed = authors(&["elm", "elm-explorations"]);
+        assert!(author_allowed("elm", &allowed, &[]));
+        assert!(!author_allowed("someone", &allowed, &[]));
+        assert!(!author_allowed("", &allowed, &[]));
+    }
+
+    #[test]
+    fn denied_authors() {
+        let denied = authors(&["someone"]);
+        assert!(author_allowed("elm", &[], &denied));
+        assert!(!author_allowed("someone", &[], &denied));
+        assert!(!author_allowed("someone", &authors(&["someone"]), &denied));
+    }
+
+    #[test]
+    fn packages_without_author() {
+        assert!(author_allowed("", &authors(&["elm", ""]), &[]));
+        assert!(!author_allowed("", &[], &authors(&[""])));
+    }
+}
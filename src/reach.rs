@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exploration of all the packages reachable from a project under its constraints.
+//!
+//! Contrary to a solve, all versions allowed by the constraints are explored,
+//! to know which future releases could change the solution.
+
+use std::collections::HashSet;
+use std::error::Error;
+
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use pubgrub::range::Range;
+use pubgrub::type_aliases::Map;
+use pubgrub::version::SemanticVersion as SemVer;
+
+use crate::provider::JsProvider;
+use crate::solver;
+
+/// Union of the constraints on each package reachable from the project.
+///
+/// Every version of a reachable package allowed by those constraints is explored,
+/// and its dependencies are reachable in turn.
+pub fn reachable_constraints(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    provider: &JsProvider,
+) -> Result<Map<Pkg, Range<SemVer>>, Box<dyn Error>> {
+    // Explore all versions, not only the most recent ones.
+    provider.remove_versions_cap();
+    let mut reach = solver::direct_dependencies(project_elm_json, use_test, &[]);
+    let mut to_explore: Vec<Pkg> = reach.keys().cloned().collect();
+    let mut explored: HashSet<(Pkg, SemVer)> = HashSet::new();
+    while let Some(pkg) = to_explore.pop() {
+        let range = reach[&pkg].clone();
+        let versions: Vec<SemVer> = match solver::exact_version(&range) {
+            Some(version) => vec![version],
+            None => (provider.list_available_versions(&pkg)?)
+                .filter(|v| range.contains(v))
+                .collect(),
+        };
+        for version in versions {
+            if !explored.insert((pkg.clone(), version)) {
+                continue;
+            }
+            let pkg_config = provider.fetch_elm_json(&pkg, version)?;
+            for (dep, constraint) in &pkg_config.dependencies {
+                let dep_range = reach.entry(dep.clone()).or_insert_with(Range::none);
+                let union = dep_range.union(&constraint.0);
+                if &union != dep_range {
+                    *dep_range = union;
+                    to_explore.push(dep.clone());
+                }
+            }
+        }
+    }
+    Ok(reach)
+}
@@ -0,0 +1,529 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conflict reports when there is no solution, as text and as structured data.
+
+use std::collections::{BTreeMap, HashMap};
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use pubgrub::range::Range;
+use pubgrub::report::{DefaultStringReporter, DerivationTree, Derived, External, Reporter};
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::options::{ReportFormat, SolveOptions};
+use crate::provider::JsProvider;
+use crate::solver;
+
+/// Derivation tree explaining why there is no solution, in a serializable form.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ConflictTree {
+    /// The root package, only available at its version.
+    #[serde(rename_all = "camelCase")]
+    NotRoot { package: Pkg, version: SemVer },
+    /// No version of the package in the range.
+    #[serde(rename_all = "camelCase")]
+    NoVersions { package: Pkg, range: String },
+    /// The dependencies of the package in the range could not be retrieved.
+    #[serde(rename_all = "camelCase")]
+    UnavailableDependencies { package: Pkg, range: String },
+    /// The package in the range depends on the dependency in its range.
+    #[serde(rename_all = "camelCase")]
+    FromDependencyOf {
+        package: Pkg,
+        range: String,
+        dependency: Pkg,
+        dependency_range: String,
+    },
+    /// Incompatibility derived from two others.
+    #[serde(rename_all = "camelCase")]
+    Derived {
+        /// Terms of the incompatibility, like `"1.0.0 <= v < 2.0.0"` or `"Not ( 1.0.0 )"`.
+        terms: BTreeMap<Pkg, String>,
+        /// Identifier of the incompatibility if it appears several times in the tree.
+        shared_id: Option<usize>,
+        cause1: Box<ConflictTree>,
+        cause2: Box<ConflictTree>,
+    },
+}
+
+impl From<&DerivationTree<Pkg, SemVer>> for ConflictTree {
+    fn from(tree: &DerivationTree<Pkg, SemVer>) -> Self {
+        match tree {
+            DerivationTree::External(External::NotRoot(package, version)) => Self::NotRoot {
+                package: package.clone(),
+                version: *version,
+            },
+            DerivationTree::External(External::NoVersions(package, range)) => Self::NoVersions {
+                package: package.clone(),
+                range: range.to_string(),
+            },
+            DerivationTree::External(External::UnavailableDependencies(package, range)) => {
+                Self::UnavailableDependencies {
+                    package: package.clone(),
+                    range: range.to_string(),
+                }
+            }
+            DerivationTree::External(External::FromDependencyOf(p1, r1, p2, r2)) => {
+                Self::FromDependencyOf {
+                    package: p1.clone(),
+                    range: r1.to_string(),
+                    dependency: p2.clone(),
+                    dependency_range: r2.to_string(),
+                }
+            }
+            DerivationTree::Derived(derived) => Self::Derived {
+                terms: (derived.terms.iter())
+                    .map(|(p, t)| (p.clone(), t.to_string()))
+                    .collect(),
+                shared_id: derived.shared_id,
+                cause1: Box::new(derived.cause1.as_ref().into()),
+                cause2: Box::new(derived.cause2.as_ref().into()),
+            },
+        }
+    }
+}
+
+/// Requirement of a package on another one, in a root cause of a conflict.
+#[derive(Serialize)]
+pub struct Requirement {
+    package: Pkg,
+    /// Versions of the package with this requirement.
+    range: String,
+    /// Versions required of the dependency.
+    requires: String,
+}
+
+/// Root cause of a conflict, at the leaves of the derivation tree.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RootCause {
+    /// Two packages require incompatible versions of the same package.
+    Incompatible {
+        package: Pkg,
+        requirements: [Requirement; 2],
+        message: String,
+    },
+    /// No version of the package is available in the range.
+    NoVersions {
+        package: Pkg,
+        range: String,
+        message: String,
+    },
+}
+
+/// Maximum number of root causes reported.
+const MAX_ROOT_CAUSES: usize = 10;
+
+/// Reduce a derivation tree to the pairs of requirements on the same package
+/// that cannot be satisfied together, and the ranges without any version.
+pub fn root_causes(tree: &DerivationTree<Pkg, SemVer>) -> Vec<RootCause> {
+    let mut leaves = Vec::new();
+    collect_leaves(tree, &mut leaves);
+    let mut causes = Vec::new();
+    let mut requirements: Vec<(&Pkg, &Range<SemVer>, &Pkg, &Range<SemVer>)> = Vec::new();
+    for leaf in &leaves {
+        if let External::FromDependencyOf(p, r, dep, dep_range) = leaf {
+            if !requirements.contains(&(p, r, dep, dep_range)) {
+                requirements.push((p, r, dep, dep_range));
+            }
+        }
+    }
+    // Ranges without versions are only root causes if a package requires one of them,
+    // otherwise they are gaps between versions, used in the proof.
+    let is_required = |pkg: &Pkg, range: &Range<SemVer>| {
+        (requirements.iter()).any(|(_, _, dep, dep_range)| {
+            *dep == pkg && &dep_range.intersection(range) == *dep_range
+        })
+    };
+    for leaf in leaves {
+        match leaf {
+            External::NoVersions(p, r) if is_required(p, r) => {
+                let range = r.to_string();
+                let duplicate = causes.iter().any(|cause| {
+                    matches!(cause, RootCause::NoVersions { package, range: r, .. }
+                        if package == p && r == &range)
+                });
+                if !duplicate {
+                    causes.push(RootCause::NoVersions {
+                        message: format!("There is no version of {} in {}", p, range),
+                        package: p.clone(),
+                        range,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut seen = Vec::new();
+    for (i, (p1, r1, dep1, dep_r1)) in requirements.iter().enumerate() {
+        for (p2, r2, dep2, dep_r2) in &requirements[i + 1..] {
+            let key = (*p1, *p2, *dep1);
+            if dep1 != dep2 || p1 == p2 || seen.contains(&key) {
+                continue;
+            }
+            if dep_r1.intersection(dep_r2) != Range::none() {
+                continue;
+            }
+            seen.push(key);
+            causes.push(RootCause::Incompatible {
+                package: (*dep1).clone(),
+                message: format!(
+                    "{} {} requires {} {}, {} {} requires {} {}",
+                    p1, r1, dep1, dep_r1, p2, r2, dep2, dep_r2
+                ),
+                requirements: [
+                    Requirement {
+                        package: (*p1).clone(),
+                        range: r1.to_string(),
+                        requires: dep_r1.to_string(),
+                    },
+                    Requirement {
+                        package: (*p2).clone(),
+                        range: r2.to_string(),
+                        requires: dep_r2.to_string(),
+                    },
+                ],
+            });
+        }
+    }
+    causes.truncate(MAX_ROOT_CAUSES);
+    causes
+}
+
+/// External incompatibilities at the leaves of the tree.
+fn collect_leaves<'a>(
+    tree: &'a DerivationTree<Pkg, SemVer>,
+    leaves: &mut Vec<&'a External<Pkg, SemVer>>,
+) {
+    match tree {
+        DerivationTree::External(external) => {
+            leaves.push(external);
+        }
+        DerivationTree::Derived(derived) => {
+            collect_leaves(&derived.cause1, leaves);
+            collect_leaves(&derived.cause2, leaves);
+        }
+    }
+}
+
+/// Text report of a conflict, in the format of the options.
+pub fn text_report(tree: &DerivationTree<Pkg, SemVer>, options: &SolveOptions) -> String {
+    let report = DefaultStringReporter::report(tree);
+    match options.report_format.unwrap_or_default() {
+        ReportFormat::Text => truncate(tree, report, options.max_report_lines),
+        ReportFormat::Sections => sectioned(tree, &report, false),
+        ReportFormat::Markdown => sectioned(tree, &report, true),
+    }
+}
+
+/// Text report of a conflict, followed by the hints of versions making a solution possible.
+pub fn text_report_with_hints(
+    tree: &DerivationTree<Pkg, SemVer>,
+    hints: &[VersionHint],
+    options: &SolveOptions,
+) -> String {
+    let mut report = text_report(tree, options);
+    for hint in hints {
+        let versions: Vec<String> = hint.versions.iter().map(|v| v.to_string()).collect();
+        report.push_str(&format!(
+            "\n\nHint: {} {} would make the resolution possible, instead of {}.",
+            hint.package,
+            versions.join(" or "),
+            hint.requested
+        ));
+    }
+    report
+}
+
+/// Versions of a project dependency that would make the resolution possible.
+#[derive(Serialize)]
+pub struct VersionHint {
+    package: Pkg,
+    /// Constraint of the project on the package.
+    requested: String,
+    /// Closest versions to the constraint making the resolution possible.
+    versions: Vec<SemVer>,
+}
+
+/// Maximum number of project dependencies for which versions are suggested.
+const MAX_HINTED_PACKAGES: usize = 3;
+/// Maximum number of versions tried per package.
+const MAX_HINT_TRIALS: usize = 10;
+/// Maximum number of versions suggested per package.
+const MAX_HINT_VERSIONS: usize = 2;
+
+/// Find the versions closest to the requested ones of the project dependencies in the conflict
+/// that would make the resolution possible, trying them one at a time with another solve.
+pub fn nearest_versions(
+    tree: &DerivationTree<Pkg, SemVer>,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    provider: &JsProvider,
+) -> Vec<VersionHint> {
+    let (root, _) = solver::root_of(project_elm_json);
+    let direct_deps =
+        solver::direct_dependencies(project_elm_json, use_test, additional_constraints);
+    let mut involved = Vec::new();
+    root_dependencies(tree, &root, &mut involved);
+    let mut hints = Vec::new();
+    for pkg in involved.into_iter().take(MAX_HINTED_PACKAGES) {
+        let requested = match direct_deps.get(&pkg) {
+            Some(requested) => requested,
+            None => continue,
+        };
+        let mut versions: Vec<SemVer> = match provider.list_available_versions(&pkg) {
+            Ok(versions) => versions.collect(),
+            Err(_) => continue,
+        };
+        versions.sort();
+        // Index of the requested versions in the sorted versions, or where they would be.
+        let first = versions.iter().position(|v| requested.contains(v));
+        let last = versions.iter().rposition(|v| requested.contains(v));
+        let (first, last) = match (first, last, requested.lowest_version()) {
+            (Some(first), Some(last), _) => (first, last),
+            (_, _, Some(lowest)) => {
+                let index = versions.partition_point(|v| v < &lowest);
+                (index, index)
+            }
+            _ => continue,
+        };
+        let distance = |i: usize| first.saturating_sub(i).max(i.saturating_sub(last));
+        let mut candidates: Vec<(usize, SemVer)> = (versions.into_iter().enumerate())
+            .filter(|(_, v)| !requested.contains(v))
+            .collect();
+        candidates.sort_by_key(|(i, _)| distance(*i));
+        let working: Vec<SemVer> = (candidates.into_iter().map(|(_, v)| v))
+            .take(MAX_HINT_TRIALS)
+            .filter(|v| {
+                let mut deps = direct_deps.clone();
+                deps.insert(pkg.clone(), Range::exact(*v));
+                solver::solve_direct_deps(project_elm_json, deps, provider).is_ok()
+            })
+            .take(MAX_HINT_VERSIONS)
+            .collect();
+        if !working.is_empty() {
+            hints.push(VersionHint {
+                requested: requested.to_string(),
+                package: pkg,
+                versions: working,
+            });
+        }
+    }
+    hints
+}
+
+/// Packages the root depends on in the derivation tree.
+fn root_dependencies(tree: &DerivationTree<Pkg, SemVer>, root: &Pkg, pkgs: &mut Vec<Pkg>) {
+    match tree {
+        DerivationTree::External(External::FromDependencyOf(p, _, dep, _)) if p == root => {
+            if !pkgs.contains(dep) {
+                pkgs.push(dep.clone());
+            }
+        }
+        DerivationTree::External(_) => {}
+        DerivationTree::Derived(derived) => {
+            root_dependencies(&derived.cause1, root, pkgs);
+            root_dependencies(&derived.cause2, root, pkgs);
+        }
+    }
+}
+
+/// Truncate a report to `max_lines` lines if provided.
+///
+/// A truncated report keeps its first lines and its conclusion on the last line,
+/// and summarizes the skipped lines with the packages they mention the most.
+fn truncate(
+    tree: &DerivationTree<Pkg, SemVer>,
+    report: String,
+    max_lines: Option<usize>,
+) -> String {
+    let max_lines = match max_lines {
+        Some(max_lines) if report.lines().count() > max_lines => max_lines.max(3),
+        _ => return report,
+    };
+    let lines: Vec<&str> = report.lines().collect();
+    let (kept, skipped) = lines[..lines.len() - 1].split_at(max_lines - 2);
+    let mut packages = Vec::new();
+    crate::solver::packages_in_tree(tree, &mut packages);
+    let mut mentions: HashMap<&Pkg, usize> = HashMap::new();
+    for line in skipped {
+        for pkg in &packages {
+            if mentions_package(line, &pkg.to_string()) {
+                *mentions.entry(pkg).or_default() += 1;
+            }
+        }
+    }
+    let mut mentions: Vec<(&Pkg, usize)> = mentions.into_iter().collect();
+    mentions.sort_by(|(p1, n1), (p2, n2)| n2.cmp(n1).then(p1.cmp(p2)));
+    let skipped_count = skipped.iter().filter(|line| !line.is_empty()).count();
+    let mut summary = format!("… and {} more incompatibilities", skipped_count);
+    if !mentions.is_empty() {
+        let most_mentioned: Vec<String> = (mentions.iter().take(3))
+            .map(|(pkg, count)| format!("{} ({})", pkg, count))
+            .collect();
+        summary.push_str(" involving ");
+        summary.push_str(&most_mentioned.join(", "));
+    }
+    let mut truncated: Vec<&str> = kept.to_vec();
+    truncated.push(&summary);
+    truncated.push(lines[lines.len() - 1]);
+    truncated.join("\n")
+}
+
+/// Group a report by conflicting packages.
+///
+/// The report of pubgrub is made of paragraphs, each explaining an incompatibility
+/// referred to by its number in the following ones, and a last one with the conclusion.
+/// Each paragraph becomes a section titled with the packages of its incompatibility,
+/// collapsible in markdown with `<details>`, and the conclusion comes last.
+fn sectioned(tree: &DerivationTree<Pkg, SemVer>, report: &str, markdown: bool) -> String {
+    let mut line_refs = LineRefs::default();
+    if let DerivationTree::Derived(derived) = tree {
+        line_refs.build_recursive(derived);
+    }
+    let paragraphs: Vec<&str> = report.split("\n\n").collect();
+    let (conclusion, details) = paragraphs.split_last().expect("split is never empty");
+    let mut sections = Vec::new();
+    for paragraph in details {
+        let id = paragraph
+            .trim_end()
+            .strip_suffix(')')
+            .and_then(|p| p.rsplit_once('('))
+            .and_then(|(_, id)| id.parse::<usize>().ok());
+        let title = match id.and_then(|id| Some((id, line_refs.refs.get(id.checked_sub(1)?)?))) {
+            Some((id, pkgs)) => format!("Conflict between {} ({})", pkgs.join(" and "), id),
+            None => "Conflict".to_string(),
+        };
+        sections.push(if markdown {
+            format!(
+                "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+                title,
+                paragraph.replace('\n', "\n\n")
+            )
+        } else {
+            format!("{}:\n  {}", title, paragraph.replace('\n', "\n  "))
+        });
+    }
+    sections.push(if markdown {
+        format!("**Conclusion**\n\n{}", conclusion.replace('\n', "\n\n"))
+    } else {
+        format!("Conclusion:\n  {}", conclusion.replace('\n', "\n  "))
+    });
+    sections.join("\n\n")
+}
+
+/// Packages of the incompatibilities referred to by number in the report of pubgrub.
+///
+/// This follows the traversal of `DefaultStringReporter` without building the text,
+/// to know which incompatibility each line reference `(n)` is about.
+#[derive(Default)]
+struct LineRefs {
+    shared_with_ref: HashMap<usize, usize>,
+    /// Packages of each incompatibility, with its line reference as index (minus one).
+    refs: Vec<Vec<String>>,
+}
+
+impl LineRefs {
+    fn add_ref(&mut self, derived: &Derived<Pkg, SemVer>) -> usize {
+        let mut pkgs: Vec<String> = derived.terms.keys().map(|p| p.to_string()).collect();
+        pkgs.sort();
+        self.refs.push(pkgs);
+        self.refs.len()
+    }
+
+    fn line_ref_of(&self, shared_id: Option<usize>) -> Option<usize> {
+        self.shared_with_ref.get(&shared_id?).cloned()
+    }
+
+    fn build_recursive(&mut self, derived: &Derived<Pkg, SemVer>) {
+        self.build_recursive_helper(derived);
+        if let Some(id) = derived.shared_id {
+            if !self.shared_with_ref.contains_key(&id) {
+                let line_ref = self.add_ref(derived);
+                self.shared_with_ref.insert(id, line_ref);
+            }
+        }
+    }
+
+    fn build_recursive_helper(&mut self, current: &Derived<Pkg, SemVer>) {
+        match (current.cause1.as_ref(), current.cause2.as_ref()) {
+            (DerivationTree::External(_), DerivationTree::External(_)) => {}
+            (DerivationTree::Derived(derived), DerivationTree::External(_))
+            | (DerivationTree::External(_), DerivationTree::Derived(derived)) => {
+                if self.line_ref_of(derived.shared_id).is_none() {
+                    self.report_recurse_one_each(derived);
+                }
+            }
+            (DerivationTree::Derived(derived1), DerivationTree::Derived(derived2)) => {
+                match (
+                    self.line_ref_of(derived1.shared_id),
+                    self.line_ref_of(derived2.shared_id),
+                ) {
+                    (Some(_), Some(_)) => {}
+                    (Some(_), None) => self.build_recursive(derived2),
+                    (None, Some(_)) => self.build_recursive(derived1),
+                    (None, None) => {
+                        self.build_recursive(derived1);
+                        if derived1.shared_id.is_some() {
+                            self.build_recursive(current);
+                        } else {
+                            self.add_ref(derived1);
+                            self.build_recursive(derived2);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn report_recurse_one_each(&mut self, derived: &Derived<Pkg, SemVer>) {
+        match (derived.cause1.as_ref(), derived.cause2.as_ref()) {
+            (DerivationTree::Derived(prior), DerivationTree::External(_))
+            | (DerivationTree::External(_), DerivationTree::Derived(prior)) => {
+                self.build_recursive(prior)
+            }
+            _ => self.build_recursive(derived),
+        }
+    }
+}
+
+/// Whether a line of a report mentions a package, and not only a package with a longer name.
+fn mentions_package(line: &str, pkg: &str) -> bool {
+    let is_name_char = |c: Option<char>| matches!(c, Some(c) if c.is_alphanumeric() || c == '-');
+    line.match_indices(pkg).any(|(i, _)| {
+        let previous = line[..i].chars().next_back();
+        let next = line[i + pkg.len()..].chars().next();
+        !is_name_char(previous) && !is_name_char(next)
+    })
+}
+
+/// JS `Error` with the text report as message,
+/// and the `report` text and the structured `tree` as fields.
+pub fn no_solution_error(
+    tree: &DerivationTree<Pkg, SemVer>,
+    hints: &[VersionHint],
+    options: &SolveOptions,
+) -> JsValue {
+    let report = text_report_with_hints(tree, hints, options);
+    log::error!("{}", &report);
+    let js_error = js_sys::Error::new(&report);
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    let js_tree = ConflictTree::from(tree)
+        .serialize(&serializer)
+        .unwrap_or(JsValue::NULL);
+    let _ = js_sys::Reflect::set(&js_error, &"report".into(), &report.into());
+    let _ = js_sys::Reflect::set(&js_error, &"tree".into(), &js_tree);
+    let js_causes = root_causes(tree)
+        .serialize(&serializer)
+        .unwrap_or(JsValue::NULL);
+    let _ = js_sys::Reflect::set(&js_error, &"rootCauses".into(), &js_causes);
+    if !hints.is_empty() {
+        let js_hints = hints.serialize(&serializer).unwrap_or(JsValue::NULL);
+        let _ = js_sys::Reflect::set(&js_error, &"hints".into(), &js_hints);
+    }
+    js_error.into()
+}
@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Software bill of materials of a solution, in the CycloneDX or SPDX JSON formats.
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::project_config::{AppDependencies, Pkg};
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Standard of the bill of materials.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    /// CycloneDX 1.5, in JSON.
+    CycloneDx,
+    /// SPDX 2.3, in JSON.
+    Spdx,
+}
+
+/// Information about the packages of a solution, not in the solution itself.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct SbomMetadata {
+    /// Name of the project, `elm-application` by default.
+    pub name: Option<String>,
+    /// Dependencies of each package, like the `graph` field of a solution.
+    pub dependencies: Option<BTreeMap<String, Vec<String>>>,
+    /// Packages of each license, like the `licenses` field of a solution.
+    pub licenses: Option<BTreeMap<String, Vec<String>>>,
+    /// SHA-1 hash of the zipball of each package, as in its `endpoint.json`.
+    pub hashes: Option<BTreeMap<String, String>>,
+    /// Creation time of the document, like `2022-01-31T12:00:00Z`.
+    pub created: Option<String>,
+    /// Unique URI of the SPDX document.
+    pub namespace: Option<String>,
+}
+
+/// Bill of materials of a solution, in the given format.
+pub fn sbom(solution: &AppDependencies, metadata: &SbomMetadata, format: SbomFormat) -> Value {
+    let licenses: BTreeMap<&str, &str> = (metadata.licenses.iter().flatten())
+        .flat_map(|(license, pkgs)| pkgs.iter().map(move |p| (p.as_str(), license.as_str())))
+        .collect();
+    let packages: Vec<Component> = (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| {
+            let name = pkg.to_string();
+            Component {
+                license: licenses.get(name.as_str()).copied(),
+                hash: (metadata.hashes.as_ref())
+                    .and_then(|hashes| hashes.get(&name))
+                    .map(String::as_str),
+                dependencies: (metadata.dependencies.as_ref())
+                    .and_then(|dependencies| dependencies.get(&name).cloned())
+                    .unwrap_or_default(),
+                pkg,
+                version: *version,
+            }
+        })
+        .collect();
+    let name = metadata.name.as_deref().unwrap_or("elm-application");
+    match format {
+        SbomFormat::CycloneDx => cyclonedx(name, solution, &packages),
+        SbomFormat::Spdx => spdx(name, solution, &packages, metadata),
+    }
+}
+
+/// Package of the solution with its metadata.
+struct Component<'a> {
+    pkg: &'a Pkg,
+    version: SemVer,
+    license: Option<&'a str>,
+    hash: Option<&'a str>,
+    dependencies: Vec<String>,
+}
+
+impl Component<'_> {
+    /// Package URL identifying the package version.
+    fn purl(&self) -> String {
+        format!(
+            "pkg:elm/{}/{}@{}",
+            self.pkg.author, self.pkg.pkg, self.version
+        )
+    }
+
+    /// SPDX identifier of the package version, with only letters, numbers, `.` and `-`.
+    fn spdx_id(&self) -> String {
+        format!(
+            "SPDXRef-Package-{}-{}-{}",
+            self.pkg.author, self.pkg.pkg, self.version
+        )
+    }
+}
+
+/// CycloneDX document, with a component per package and the dependency graph.
+fn cyclonedx(name: &str, solution: &AppDependencies, packages: &[Component]) -> Value {
+    let purls: BTreeMap<String, String> = (packages.iter())
+        .map(|c| (c.pkg.to_string(), c.purl()))
+        .collect();
+    let components: Vec<Value> = (packages.iter())
+        .map(|c| {
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": c.purl(),
+                "group": c.pkg.author,
+                "name": c.pkg.pkg,
+                "version": c.version.to_string(),
+                "purl": c.purl(),
+            });
+            if let Some(license) = c.license {
+                component["licenses"] = json!([{ "license": { "id": license } }]);
+            }
+            if let Some(hash) = c.hash {
+                component["hashes"] = json!([{ "alg": "SHA-1", "content": hash }]);
+            }
+            component
+        })
+        .collect();
+    let direct: Vec<&String> = solution
+        .direct
+        .keys()
+        .filter_map(|p| purls.get(&p.to_string()))
+        .collect();
+    let mut dependencies = vec![json!({ "ref": name, "dependsOn": direct })];
+    for c in packages {
+        let depends_on: Vec<&String> = c.dependencies.iter().filter_map(|d| purls.get(d)).collect();
+        dependencies.push(json!({ "ref": c.purl(), "dependsOn": depends_on }));
+    }
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "component": { "type": "application", "bom-ref": name, "name": name },
+        },
+        "components": components,
+        "dependencies": dependencies,
+    })
+}
+
+/// SPDX document, with a package per package of the solution
+/// and `DEPENDS_ON` relationships for the dependency graph.
+fn spdx(
+    name: &str,
+    solution: &AppDependencies,
+    packages: &[Component],
+    metadata: &SbomMetadata,
+) -> Value {
+    let root_id = "SPDXRef-Root";
+    let ids: BTreeMap<String, String> = (packages.iter())
+        .map(|c| (c.pkg.to_string(), c.spdx_id()))
+        .collect();
+    let mut spdx_packages = vec![json!({
+        "SPDXID": root_id,
+        "name": name,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": "NOASSERTION",
+        "copyrightText": "NOASSERTION",
+    })];
+    for c in packages {
+        let mut package = json!({
+            "SPDXID": c.spdx_id(),
+            "name": c.pkg.to_string(),
+            "versionInfo": c.version.to_string(),
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": c.license.unwrap_or("NOASSERTION"),
+            "copyrightText": "NOASSERTION",
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": c.purl(),
+            }],
+        });
+        if let Some(hash) = c.hash {
+            package["checksums"] = json!([{ "algorithm": "SHA1", "checksumValue": hash }]);
+        }
+        spdx_packages.push(package);
+    }
+    let depends_on = |from: &str, to: &str| {
+        json!({
+            "spdxElementId": from,
+            "relationshipType": "DEPENDS_ON",
+            "relatedSpdxElement": to,
+        })
+    };
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": root_id,
+    })];
+    for pkg in solution.direct.keys() {
+        if let Some(id) = ids.get(&pkg.to_string()) {
+            relationships.push(depends_on(root_id, id));
+        }
+    }
+    for c in packages {
+        for id in c.dependencies.iter().filter_map(|d| ids.get(d)) {
+            relationships.push(depends_on(&c.spdx_id(), id));
+        }
+    }
+    let created = metadata.created.as_deref().unwrap_or_default();
+    let namespace = (metadata.namespace.clone())
+        .unwrap_or_else(|| format!("https://spdx.org/spdxdocs/{}-{}", name, created));
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": namespace,
+        "creationInfo": {
+            "created": created,
+            "creators": ["Tool: elm-solve-deps-wasm"],
+        },
+        "packages": spdx_packages,
+        "relationships": relationships,
+    })
+}
@@ -0,0 +1,381 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dependency solving of an elm project with our own dependency provider.
+//!
+//! This mirrors `elm_solve_deps::solver::solve_deps_with`,
+//! but lets the dependency provider control how packages and versions are picked.
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::dependency_provider::ProjectAdapter;
+use elm_solve_deps::project_config::{AppDependencies, ApplicationConfig, Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::range::Range;
+use pubgrub::report::{DerivationTree, External};
+use pubgrub::solver::DependencyProvider;
+use pubgrub::type_aliases::Map;
+use pubgrub::version::SemanticVersion as SemVer;
+
+use crate::formats;
+use crate::provider::JsProvider;
+
+/// Solve dependencies of the project with the given dependency provider.
+///
+/// Set `use_test` to true to include test dependencies in the resolution.
+pub fn solve_deps<DP: DependencyProvider<Pkg, SemVer>>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    deps_provider: &DP,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    let direct_deps = direct_dependencies(project_elm_json, use_test, additional_constraints);
+    solve_direct_deps(project_elm_json, direct_deps, deps_provider)
+}
+
+/// Solve the project with the given direct dependencies, instead of the ones in its elm.json.
+pub fn solve_direct_deps<DP: DependencyProvider<Pkg, SemVer>>(
+    project_elm_json: &ProjectConfig,
+    direct_deps: Map<Pkg, Range<SemVer>>,
+    deps_provider: &DP,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    let (root_pkg, root_version) = root_of(project_elm_json);
+
+    // Transform the generic dependency provider into one that is specific for the current project.
+    let project_deps_provider =
+        ProjectAdapter::new(root_pkg.clone(), root_version, &direct_deps, deps_provider);
+
+    // Solve dependencies and remove the root dependency from the solution.
+    let mut solution =
+        pubgrub::solver::resolve(&project_deps_provider, root_pkg.clone(), root_version)?;
+    solution.remove(&root_pkg);
+
+    // Split solution into direct and indirect deps.
+    let (direct, indirect) = solution
+        .into_iter()
+        .partition(|(pkg, _)| direct_deps.contains_key(pkg));
+    Ok(AppDependencies { direct, indirect })
+}
+
+/// Solve dependencies with the JS provider.
+///
+/// With `two_phase`, when solving with test dependencies,
+/// the normal dependencies are solved first and frozen,
+/// and only then the test dependencies are solved on top,
+/// like the elm compiler does.
+pub fn solve_deps_js(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    provider: &JsProvider,
+    two_phase: bool,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    if !(use_test && two_phase) {
+        return solve_with_fallback(project_elm_json, use_test, additional_constraints, provider);
+    }
+    let normal_solution =
+        solve_with_fallback(project_elm_json, false, additional_constraints, provider)?;
+    let mut frozen_constraints = additional_constraints.to_vec();
+    frozen_constraints.extend(exact_constraints(&normal_solution));
+    let solution = solve_with_fallback(project_elm_json, true, &frozen_constraints, provider)?;
+    // Frozen packages are not all direct dependencies, so we need to split the solution again.
+    let direct_deps = direct_dependencies(project_elm_json, true, additional_constraints);
+    Ok(split_direct(solution, &direct_deps))
+}
+
+/// Split a solution into direct and indirect dependencies.
+fn split_direct(
+    solution: AppDependencies,
+    direct_deps: &Map<Pkg, Range<SemVer>>,
+) -> AppDependencies {
+    let (direct, indirect) = (solution.direct.into_iter())
+        .chain(solution.indirect)
+        .partition(|(pkg, _)| direct_deps.contains_key(pkg));
+    AppDependencies { direct, indirect }
+}
+
+/// Solve only test dependencies with the JS provider.
+///
+/// The versions recorded in an application `dependencies` are kept fixed,
+/// and for a package, normal dependencies are solved first and kept fixed.
+/// The returned solution only contains test dependencies,
+/// and considers additional constraints as direct test dependencies.
+pub fn solve_test_deps_js(
+    project_elm_json: &ProjectConfig,
+    additional_constraints: &[(Pkg, Constraint)],
+    provider: &JsProvider,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    let normal_deps = match project_elm_json {
+        ProjectConfig::Application(app_config) => app_config.dependencies.clone(),
+        ProjectConfig::Package(_) => solve_with_fallback(project_elm_json, false, &[], provider)?,
+    };
+    let mut frozen_constraints = additional_constraints.to_vec();
+    frozen_constraints.extend(exact_constraints(&normal_deps));
+    let solution = solve_with_fallback(project_elm_json, true, &frozen_constraints, provider)?;
+
+    // Only keep test dependencies.
+    let is_normal_dep =
+        |pkg: &Pkg| normal_deps.direct.contains_key(pkg) || normal_deps.indirect.contains_key(pkg);
+    let test_direct: Vec<&Pkg> = match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            app_config.test_dependencies.direct.keys().collect()
+        }
+        ProjectConfig::Package(pkg_config) => pkg_config.test_dependencies.keys().collect(),
+    };
+    let (direct, indirect) = (solution.direct.into_iter())
+        .chain(solution.indirect)
+        .filter(|(pkg, _)| !is_normal_dep(pkg))
+        .partition(|(pkg, _)| {
+            test_direct.contains(&pkg) || additional_constraints.iter().any(|(p, _)| p == pkg)
+        });
+    Ok(AppDependencies { direct, indirect })
+}
+
+/// Solve the dependencies needed to compile and run the tests of a project.
+///
+/// Normal and test dependencies are resolved together, and all become dependencies
+/// of an application-style configuration, like the tests runner application
+/// generated by elm-test-rs for a package.
+/// Its source directories are `src` and `tests`.
+pub fn solve_tests_config_js(
+    project_elm_json: &ProjectConfig,
+    additional_constraints: &[(Pkg, Constraint)],
+    target_elm_version: SemVer,
+    provider: &JsProvider,
+) -> Result<ApplicationConfig, PubGrubError<Pkg, SemVer>> {
+    let solution = solve_with_fallback(project_elm_json, true, additional_constraints, provider)?;
+    let elm_version = match project_elm_json {
+        ProjectConfig::Application(app_config) => app_config.elm_version,
+        ProjectConfig::Package(pkg_config) => {
+            let range = &pkg_config.elm_version.0;
+            if range.contains(&target_elm_version) {
+                target_elm_version
+            } else {
+                range.lowest_version().unwrap_or(target_elm_version)
+            }
+        }
+    };
+    let source_directories = vec!["src".to_string(), "tests".to_string()];
+    Ok(formats::tests_application(
+        solution,
+        elm_version,
+        source_directories,
+    ))
+}
+
+/// Direct dependencies of a new application created by `elm init`.
+pub const ELM_INIT_PACKAGES: [&str; 3] = ["elm/browser", "elm/core", "elm/html"];
+
+/// Errors when solving the dependencies of a new application.
+pub enum NewApplicationError {
+    /// Error unrelated to a specific starter package.
+    Solve(PubGrubError<Pkg, SemVer>),
+    /// Starter packages that could not be added to the previous ones, with their errors.
+    Conflicts(Vec<(Pkg, PubGrubError<Pkg, SemVer>)>),
+}
+
+/// Solve the dependencies of a new application, like `elm init` does,
+/// with the given starter packages as direct dependencies.
+///
+/// `elm/core` is always added to the starter packages.
+/// When there is no solution, starter packages are added one at a time,
+/// to report the ones conflicting with the previous ones.
+pub fn solve_new_application_js(
+    elm_version: SemVer,
+    starter_packages: &[Pkg],
+    provider: &JsProvider,
+) -> Result<ApplicationConfig, NewApplicationError> {
+    let empty_deps = AppDependencies {
+        direct: BTreeMap::new(),
+        indirect: BTreeMap::new(),
+    };
+    let mut app_config = ApplicationConfig {
+        source_directories: vec!["src".to_string()],
+        elm_version,
+        dependencies: empty_deps.clone(),
+        test_dependencies: empty_deps,
+    };
+    let new_project = ProjectConfig::Application(app_config.clone());
+    let mut packages = vec![Pkg::new("elm", "core")];
+    for pkg in starter_packages {
+        if !packages.contains(pkg) {
+            packages.push(pkg.clone());
+        }
+    }
+    let solve = |packages: &[Pkg]| {
+        let constraints: Vec<(Pkg, Constraint)> = (packages.iter())
+            .map(|p| (p.clone(), Constraint(Range::any())))
+            .collect();
+        solve_with_fallback(&new_project, false, &constraints, provider)
+    };
+
+    match solve(&packages) {
+        Ok(solution) => {
+            app_config.dependencies = solution;
+            return Ok(app_config);
+        }
+        Err(PubGrubError::NoSolution(_)) => {}
+        Err(err) => return Err(NewApplicationError::Solve(err)),
+    }
+
+    // Find the starter packages that conflict with the previous ones.
+    let mut accepted = Vec::new();
+    let mut conflicts = Vec::new();
+    for pkg in packages {
+        accepted.push(pkg);
+        match solve(&accepted) {
+            Ok(_) => {}
+            Err(err @ PubGrubError::NoSolution(_)) => {
+                conflicts.push((accepted.pop().unwrap(), err));
+            }
+            Err(err) => return Err(NewApplicationError::Solve(err)),
+        }
+    }
+    Err(NewApplicationError::Conflicts(conflicts))
+}
+
+/// Part of a merged configuration responsible for a failed resolution.
+#[derive(Debug, Clone, Copy)]
+pub enum Culprit {
+    /// The base configuration has no solution on its own.
+    Base,
+    /// The other configuration has no solution on its own.
+    Other,
+    /// Both have a solution on their own, but not once merged.
+    Merge,
+}
+
+/// Solve the dependencies of a base configuration merged with the ones of another.
+///
+/// The dependencies of the other configuration are added as constraints,
+/// with their exact versions for an application, and their ranges for a package.
+/// When there is no solution, each configuration is solved on its own
+/// to find which one causes the conflict, and the error of that resolution is returned.
+pub fn solve_merged_js(
+    base_elm_json: &ProjectConfig,
+    other_elm_json: &ProjectConfig,
+    provider: &JsProvider,
+) -> Result<AppDependencies, (Option<Culprit>, PubGrubError<Pkg, SemVer>)> {
+    let other_constraints: Vec<(Pkg, Constraint)> =
+        (direct_dependencies(other_elm_json, false, &[]).into_iter())
+            .map(|(p, r)| (p, Constraint(r)))
+            .collect();
+    let merge_err = match solve_with_fallback(base_elm_json, false, &other_constraints, provider) {
+        Err(err @ PubGrubError::NoSolution(_)) => err,
+        result => return result.map_err(|err| (None, err)),
+    };
+    for (culprit, elm_json) in [
+        (Culprit::Base, base_elm_json),
+        (Culprit::Other, other_elm_json),
+    ] {
+        match solve_with_fallback(elm_json, false, &[], provider) {
+            Ok(_) => {}
+            Err(err @ PubGrubError::NoSolution(_)) => return Err((Some(culprit), err)),
+            Err(err) => return Err((None, err)),
+        }
+    }
+    Err((Some(Culprit::Merge), merge_err))
+}
+
+/// Solve dependencies with the JS provider.
+///
+/// If the number of versions explored per package is capped,
+/// and no solution exists with that cap, solve again with all versions.
+fn solve_with_fallback(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    provider: &JsProvider,
+) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+    let result = solve_deps(project_elm_json, use_test, additional_constraints, provider);
+    match result {
+        Err(PubGrubError::NoSolution(_)) if provider.remove_versions_cap() => {
+            log::info!("No solution with capped versions, solving again with all versions");
+            solve_deps(project_elm_json, use_test, additional_constraints, provider)
+        }
+        _ => result,
+    }
+}
+
+/// Constraints pinning all packages of a solution to their exact version.
+pub fn exact_constraints(solution: &AppDependencies) -> Vec<(Pkg, Constraint)> {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(p, v)| (p.clone(), Constraint(Range::exact(*v))))
+        .collect()
+}
+
+/// Package and version used as root of the dependency resolution.
+pub fn root_of(project_elm_json: &ProjectConfig) -> (Pkg, SemVer) {
+    match project_elm_json {
+        ProjectConfig::Application(_) => (Pkg::new("root", ""), SemVer::zero()),
+        ProjectConfig::Package(pkg_config) => (pkg_config.name.clone(), pkg_config.version),
+    }
+}
+
+/// Direct dependencies of the project, merged with test dependencies if `use_test`,
+/// and with the additional constraints.
+pub fn direct_dependencies(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+) -> Map<Pkg, Range<SemVer>> {
+    let mut direct_deps: Map<Pkg, Range<SemVer>> = match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            let normal_deps = app_config.dependencies.direct.iter();
+            let test_deps = app_config.test_dependencies.direct.iter();
+            let test_deps = test_deps.filter(|_| use_test);
+            normal_deps
+                .chain(test_deps)
+                .map(|(p, v)| (p.clone(), Range::exact(*v)))
+                .collect()
+        }
+        ProjectConfig::Package(pkg_config) => {
+            let normal_deps = pkg_config.dependencies.iter();
+            let test_deps = pkg_config.test_dependencies.iter();
+            let test_deps = test_deps.filter(|_| use_test);
+            normal_deps
+                .chain(test_deps)
+                .map(|(p, c)| (p.clone(), c.0.clone()))
+                .collect()
+        }
+    };
+    // Include the additional constraints.
+    for (p, r) in additional_constraints {
+        let dep_range = direct_deps.entry(p.clone()).or_insert_with(Range::any);
+        *dep_range = dep_range.intersection(&r.0);
+    }
+    direct_deps
+}
+
+/// Return the version if the range contains exactly one version.
+pub fn exact_version(range: &Range<SemVer>) -> Option<SemVer> {
+    let version = range.lowest_version()?;
+    if range == &Range::exact(version) {
+        Some(version)
+    } else {
+        None
+    }
+}
+
+/// Collect all the packages involved in the derivation tree of a failed resolution.
+pub fn packages_in_tree(tree: &DerivationTree<Pkg, SemVer>, packages: &mut Vec<Pkg>) {
+    let mut add = |pkg: &Pkg| {
+        if !packages.contains(pkg) {
+            packages.push(pkg.clone());
+        }
+    };
+    match tree {
+        DerivationTree::External(External::NotRoot(p, _)) => add(p),
+        DerivationTree::External(External::NoVersions(p, _)) => add(p),
+        DerivationTree::External(External::UnavailableDependencies(p, _)) => add(p),
+        DerivationTree::External(External::FromDependencyOf(p1, _, p2, _)) => {
+            add(p1);
+            add(p2);
+        }
+        DerivationTree::Derived(derived) => {
+            packages_in_tree(&derived.cause1, packages);
+            packages_in_tree(&derived.cause2, packages);
+        }
+    }
+}
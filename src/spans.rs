@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Timing spans of the solver, forwarded to a JS subscriber.
+//!
+//! Spans cover the phases of a solve, each call to the JS callbacks,
+//! and each pubgrub decision of a package version.
+//! Nothing is recorded when no subscriber is set.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    static SUBSCRIBER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+/// Set the JS function receiving span events, or remove it with `None`.
+pub fn set_subscriber(subscriber: Option<js_sys::Function>) {
+    SUBSCRIBER.with(|s| *s.borrow_mut() = subscriber);
+}
+
+fn is_subscribed() -> bool {
+    SUBSCRIBER.with(|s| s.borrow().is_some())
+}
+
+/// A span, sending its `enter` event when created, and its `exit` event when dropped.
+pub struct Span(Option<ActiveSpan>);
+
+struct ActiveSpan {
+    name: &'static str,
+    start: f64,
+    fields: js_sys::Object,
+}
+
+impl Span {
+    /// Enter a span, with fields describing it, like the package concerned.
+    pub fn enter(name: &'static str, fields: &[(&str, &dyn ToString)]) -> Self {
+        if !is_subscribed() {
+            return Self(None);
+        }
+        let span = ActiveSpan {
+            name,
+            start: js_sys::Date::now(),
+            fields: js_sys::Object::new(),
+        };
+        for (key, value) in fields {
+            set(&span.fields, key, &JsValue::from_str(&value.to_string()));
+        }
+        span.send("enter", None);
+        Self(Some(span))
+    }
+
+    /// Add a field to the span, reported in its `exit` event.
+    pub fn record(&self, key: &str, value: &dyn ToString) {
+        if let Some(span) = &self.0 {
+            set(&span.fields, key, &JsValue::from_str(&value.to_string()));
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(span) = &self.0 {
+            let duration = js_sys::Date::now() - span.start;
+            span.send("exit", Some(duration));
+        }
+    }
+}
+
+impl ActiveSpan {
+    /// Send an event `{ kind, name, fields, time, duration }` to the subscriber.
+    /// Errors thrown by the subscriber are ignored.
+    fn send(&self, kind: &str, duration: Option<f64>) {
+        let event = js_sys::Object::new();
+        set(&event, "kind", &JsValue::from_str(kind));
+        set(&event, "name", &JsValue::from_str(self.name));
+        set(&event, "fields", &self.fields);
+        set(&event, "time", &JsValue::from_f64(js_sys::Date::now()));
+        if let Some(duration) = duration {
+            set(&event, "duration", &JsValue::from_f64(duration));
+        }
+        SUBSCRIBER.with(|s| {
+            if let Some(subscriber) = s.borrow().as_ref() {
+                let _ = subscriber.call1(&JsValue::NULL, &event);
+            }
+        });
+    }
+}
+
+fn set(object: &js_sys::Object, key: &str, value: &JsValue) {
+    let _ = js_sys::Reflect::set(object, &JsValue::from_str(key), value);
+}
@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Statistics about the resources used by the solver.
+
+#[cfg(not(feature = "minimal"))]
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+/// Size of a wasm memory page.
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// Memory usage of this wasm instance.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    /// Number of wasm memory pages (64kb each) currently reserved.
+    memory_pages: usize,
+    /// Same as `memory_pages` but in bytes.
+    memory_bytes: usize,
+    /// Bytes currently allocated on the heap, unknown with the `minimal` feature.
+    allocated_bytes: Option<usize>,
+    /// Maximum of bytes allocated on the heap during the last solve,
+    /// unknown with the `minimal` feature.
+    peak_allocated_bytes: Option<usize>,
+    /// Estimated size of the caches kept by the last solve.
+    cache_bytes: usize,
+}
+
+/// Statistics of the calls to the JS callbacks during a solve.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStats {
+    /// Number of calls to `fetch_elm_json`.
+    pub fetch_elm_json_calls: usize,
+    /// Number of `fetch_elm_json` calls avoided because the elm.json was already fetched.
+    pub fetch_elm_json_avoided: usize,
+    /// Number of calls to `list_available_versions`.
+    pub list_available_versions_calls: usize,
+    /// Number of `list_available_versions` calls avoided because versions were already listed.
+    pub list_available_versions_avoided: usize,
+}
+
+impl CallStats {
+    /// Statistics before any call.
+    pub const fn new() -> Self {
+        Self {
+            fetch_elm_json_calls: 0,
+            fetch_elm_json_avoided: 0,
+            list_available_versions_calls: 0,
+            list_available_versions_avoided: 0,
+        }
+    }
+}
+
+/// Time spent in the JS callbacks, and bytes of elm.json parsed, during a solve.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SolvePerf {
+    /// Milliseconds spent in the JS callbacks.
+    pub callback_ms: f64,
+    /// Bytes of elm.json documents parsed.
+    pub bytes_parsed: usize,
+}
+
+/// Performance counters accumulated across solves, until reset.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfCounters {
+    /// Number of solves.
+    solves: usize,
+    /// Milliseconds spent in solves, callbacks included.
+    total_ms: f64,
+    /// Milliseconds spent in the JS callbacks.
+    callback_ms: f64,
+    /// Milliseconds spent in the solver itself, outside of the JS callbacks.
+    solver_ms: f64,
+    /// Bytes of elm.json documents parsed.
+    bytes_parsed: usize,
+    /// Calls to the JS callbacks and calls avoided thanks to caches.
+    calls: CallStats,
+    /// Ratio of `fetch_elm_json` calls avoided thanks to the cache.
+    elm_json_cache_hit_rate: f64,
+    /// Ratio of `list_available_versions` calls avoided thanks to the cache.
+    versions_cache_hit_rate: f64,
+}
+
+impl PerfCounters {
+    const fn new() -> Self {
+        Self {
+            solves: 0,
+            total_ms: 0.0,
+            callback_ms: 0.0,
+            solver_ms: 0.0,
+            bytes_parsed: 0,
+            calls: CallStats::new(),
+            elm_json_cache_hit_rate: 0.0,
+            versions_cache_hit_rate: 0.0,
+        }
+    }
+}
+
+/// Error when the memory used goes over the limit set in the options.
+#[derive(Debug)]
+pub struct MemoryBudgetExceeded {
+    /// Bytes allocated when the limit was checked.
+    pub allocated: usize,
+    /// Configured limit, in bytes.
+    pub limit: usize,
+}
+
+impl fmt::Display for MemoryBudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Memory budget exceeded: {} bytes are allocated, over the limit of {} bytes",
+            self.allocated, self.limit
+        )
+    }
+}
+
+impl std::error::Error for MemoryBudgetExceeded {}
+
+/// Global allocator wrapper, counting the currently allocated bytes.
+/// Not used with the `minimal` feature, to spare the counting on every allocation.
+#[cfg(not(feature = "minimal"))]
+pub struct CountingAlloc<A>(pub A);
+
+#[cfg(not(feature = "minimal"))]
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(not(feature = "minimal"))]
+static PEAK_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static LAST_CACHE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static LAST_CALL_STATS: Cell<CallStats> = const { Cell::new(CallStats::new()) };
+    static PERF_COUNTERS: Cell<PerfCounters> = const { Cell::new(PerfCounters::new()) };
+}
+
+#[cfg(not(feature = "minimal"))]
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc(layout);
+        if !ptr.is_null() {
+            count_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.0.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            count_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(not(feature = "minimal"))]
+fn count_alloc(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_ALLOCATED.fetch_max(allocated, Ordering::Relaxed);
+}
+
+/// Bytes currently allocated on the heap.
+#[cfg(not(feature = "minimal"))]
+pub fn allocated_bytes() -> Option<usize> {
+    Some(ALLOCATED.load(Ordering::Relaxed))
+}
+
+#[cfg(feature = "minimal")]
+pub fn allocated_bytes() -> Option<usize> {
+    None
+}
+
+#[cfg(not(feature = "minimal"))]
+fn peak_allocated_bytes() -> Option<usize> {
+    Some(PEAK_ALLOCATED.load(Ordering::Relaxed))
+}
+
+#[cfg(feature = "minimal")]
+fn peak_allocated_bytes() -> Option<usize> {
+    None
+}
+
+/// Reset the peak memory usage at the beginning of a solve.
+pub fn start_solve() {
+    #[cfg(not(feature = "minimal"))]
+    PEAK_ALLOCATED.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Record the estimated size of the caches and the calls statistics at the end of a solve.
+pub fn end_solve(cache_bytes: usize, call_stats: CallStats) {
+    LAST_CACHE_BYTES.store(cache_bytes, Ordering::Relaxed);
+    LAST_CALL_STATS.with(|s| s.set(call_stats));
+}
+
+/// Statistics of the calls to the JS callbacks made by the last solve.
+pub fn last_call_stats() -> CallStats {
+    LAST_CALL_STATS.with(Cell::get)
+}
+
+/// Add a solve to the performance counters.
+pub fn record_perf(total_ms: f64, perf: SolvePerf, calls: CallStats) {
+    PERF_COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.solves += 1;
+        c.total_ms += total_ms;
+        c.callback_ms += perf.callback_ms;
+        c.solver_ms = c.total_ms - c.callback_ms;
+        c.bytes_parsed += perf.bytes_parsed;
+        c.calls.fetch_elm_json_calls += calls.fetch_elm_json_calls;
+        c.calls.fetch_elm_json_avoided += calls.fetch_elm_json_avoided;
+        c.calls.list_available_versions_calls += calls.list_available_versions_calls;
+        c.calls.list_available_versions_avoided += calls.list_available_versions_avoided;
+        c.elm_json_cache_hit_rate =
+            hit_rate(c.calls.fetch_elm_json_avoided, c.calls.fetch_elm_json_calls);
+        c.versions_cache_hit_rate = hit_rate(
+            c.calls.list_available_versions_avoided,
+            c.calls.list_available_versions_calls,
+        );
+        counters.set(c);
+    });
+}
+
+fn hit_rate(hits: usize, misses: usize) -> f64 {
+    if hits + misses == 0 {
+        0.0
+    } else {
+        hits as f64 / (hits + misses) as f64
+    }
+}
+
+/// Performance counters accumulated since the last reset.
+pub fn perf_counters() -> PerfCounters {
+    PERF_COUNTERS.with(Cell::get)
+}
+
+/// Reset the performance counters.
+pub fn reset_perf_counters() {
+    PERF_COUNTERS.with(|c| c.set(PerfCounters::new()));
+}
+
+/// Current time in milliseconds, with sub-millisecond precision.
+/// There is no JS `performance` for WASI hosts, so timings are not measured there.
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+pub fn now_ms() -> f64 {
+    performance_now()
+}
+
+#[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"))))]
+pub fn now_ms() -> f64 {
+    0.0
+}
+
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// Current memory usage.
+pub fn memory_stats() -> MemoryStats {
+    let memory_pages = memory_pages();
+    MemoryStats {
+        memory_pages,
+        memory_bytes: memory_pages * PAGE_SIZE,
+        allocated_bytes: allocated_bytes(),
+        peak_allocated_bytes: peak_allocated_bytes(),
+        cache_bytes: LAST_CACHE_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn memory_pages() -> usize {
+    core::arch::wasm32::memory_size(0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn memory_pages() -> usize {
+    0
+}
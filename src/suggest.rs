@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Suggestions to improve the constraints of packages.
+
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::constraints::{self, AdditionalConstraint};
+use crate::provider::JsProvider;
+use crate::solver;
+
+/// Suggestion to loosen the constraint of a dependency.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstraintSuggestion {
+    /// The dependency.
+    package: Pkg,
+    /// Its current constraint.
+    current: AdditionalConstraint,
+    /// The widest constraint with which the dependencies still have a solution.
+    widest: AdditionalConstraint,
+}
+
+/// Compute, for each dependency of a package, the widest constraint
+/// with which the dependencies still have a solution, with the other constraints unchanged.
+///
+/// The current constraint is extended with consecutive versions below it,
+/// and with complete major versions above it,
+/// as long as the dependencies have a solution with each of those versions.
+/// Only dependencies whose constraint can be loosened are returned.
+pub fn widest_constraints(
+    project_elm_json: &ProjectConfig,
+    provider: &JsProvider,
+) -> Result<Vec<ConstraintSuggestion>, PubGrubError<Pkg, SemVer>> {
+    // Explore all versions, not only the most recent ones.
+    provider.remove_versions_cap();
+    let direct_deps = solver::direct_dependencies(project_elm_json, false, &[]);
+    solver::solve_direct_deps(project_elm_json, direct_deps.clone(), provider)?;
+
+    let mut suggestions = Vec::new();
+    let mut dependencies: Vec<_> = direct_deps.iter().collect();
+    dependencies.sort_by_key(|(pkg, _)| *pkg);
+    for (pkg, range) in dependencies {
+        let mut versions: Vec<SemVer> = provider
+            .list_available_versions(pkg)
+            .map_err(PubGrubError::ErrorChoosingPackageVersion)?
+            .collect();
+        versions.sort();
+        let low = match versions.iter().find(|v| range.contains(v)) {
+            Some(low) => *low,
+            None => continue,
+        };
+        let high = *versions.iter().rev().find(|v| range.contains(v)).unwrap();
+
+        // Check that the dependencies have a solution with the given version of pkg.
+        let solvable = |version: SemVer| {
+            let mut deps = direct_deps.clone();
+            deps.insert(pkg.clone(), Range::exact(version));
+            match solver::solve_direct_deps(project_elm_json, deps, provider) {
+                Ok(_) => Ok(true),
+                Err(PubGrubError::NoSolution(_)) => Ok(false),
+                Err(err) => Err(err),
+            }
+        };
+        let mut widest = range.clone();
+        for &version in versions.iter().rev().filter(|v| **v < low) {
+            if !solvable(version)? {
+                break;
+            }
+            widest = widest.union(&Range::between(version, low));
+        }
+        // The upper bound must be a major version, so extend it one major at a time,
+        // only if all versions of that major are solvable.
+        let mut higher_versions = versions.iter().filter(|v| **v > high).peekable();
+        'majors: while let Some(&&first) = higher_versions.peek() {
+            let next_major = first.bump_major();
+            while let Some(&version) = higher_versions.next_if(|v| **v < next_major) {
+                if !solvable(version)? {
+                    break 'majors;
+                }
+            }
+            widest = widest.union(&Range::between(low, next_major));
+        }
+        if &widest != range {
+            suggestions.push(ConstraintSuggestion {
+                package: pkg.clone(),
+                current: constraints::format(range),
+                widest: constraints::format(&widest),
+            });
+        }
+    }
+    Ok(suggestions)
+}
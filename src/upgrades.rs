@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Reports of the newer versions available for the packages of a project.
+
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use pubgrub::error::PubGrubError;
+use pubgrub::range::Range;
+use pubgrub::report::{DerivationTree, External};
+use pubgrub::type_aliases::Map;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+
+use crate::provider::JsProvider;
+use crate::solver;
+
+/// Newer versions available for a package of the solution.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageUpgrades {
+    package: Pkg,
+    /// Version in the solution.
+    current: SemVer,
+    /// True if the package is a direct dependency.
+    direct: bool,
+    /// Latest version with the same major and minor, if newer.
+    patch: Option<Upgrade>,
+    /// Latest version with the same major, if it has a newer minor.
+    minor: Option<Upgrade>,
+    /// Latest version, if it has a newer major.
+    major: Option<Upgrade>,
+}
+
+/// A newer version and what prevents adopting it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Upgrade {
+    version: SemVer,
+    /// True if the dependencies have a solution with this version.
+    solvable: bool,
+    /// True if the project own constraint on the package excludes this version.
+    own_constraint: bool,
+    /// Packages whose dependencies exclude this version.
+    blocked_by: Vec<Pkg>,
+}
+
+/// Report, for each package of the solution, the newer patch, minor and major versions,
+/// and what prevents adopting them.
+///
+/// The constraints of an application are its exact versions,
+/// so they are not considered as preventing an upgrade,
+/// but the other direct dependencies stay at their versions.
+pub fn upgrades(
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+    provider: &JsProvider,
+) -> Result<Vec<PackageUpgrades>, PubGrubError<Pkg, SemVer>> {
+    // Consider all versions, not only the most recent ones.
+    provider.remove_versions_cap();
+    let direct_deps = solver::direct_dependencies(project_elm_json, false, &[]);
+    let (root_pkg, _) = solver::root_of(project_elm_json);
+    let is_app = matches!(project_elm_json, ProjectConfig::Application(_));
+
+    let mut report = Vec::new();
+    let packages = (solution.direct.iter().map(|(p, v)| (p, v, true)))
+        .chain(solution.indirect.iter().map(|(p, v)| (p, v, false)));
+    for (pkg, &current, direct) in packages {
+        let mut versions: Vec<SemVer> = provider
+            .list_available_versions(pkg)
+            .map_err(PubGrubError::ErrorChoosingPackageVersion)?
+            .filter(|v| v > &current)
+            .collect();
+        versions.sort();
+        let (major, minor, _) = current.into();
+        let latest_where = |same: &dyn Fn(u32, u32) -> bool| {
+            (versions.iter().rev())
+                .find(|v| {
+                    let (v_major, v_minor, _) = (**v).into();
+                    same(v_major, v_minor)
+                })
+                .copied()
+        };
+        let patch = latest_where(&|ma, mi| ma == major && mi == minor);
+        let minor = latest_where(&|ma, mi| ma == major && mi > minor);
+        let major = latest_where(&|ma, _| ma > major);
+
+        let check = |version: Option<SemVer>| -> Result<_, PubGrubError<Pkg, SemVer>> {
+            let version = match version {
+                Some(version) => version,
+                None => return Ok(None),
+            };
+            let own_constraint =
+                !is_app && matches!(direct_deps.get(pkg), Some(range) if !range.contains(&version));
+            let mut deps = direct_deps.clone();
+            deps.insert(pkg.clone(), Range::exact(version));
+            let (solvable, blocked_by) =
+                match solver::solve_direct_deps(project_elm_json, deps, provider) {
+                    Ok(_) => (true, Vec::new()),
+                    Err(PubGrubError::NoSolution(tree)) => {
+                        let mut blocked_by = Vec::new();
+                        blocking_packages(&tree, pkg, &root_pkg, &mut blocked_by);
+                        (false, blocked_by)
+                    }
+                    Err(err) => return Err(err),
+                };
+            Ok(Some(Upgrade {
+                version,
+                solvable,
+                own_constraint,
+                blocked_by,
+            }))
+        };
+        report.push(PackageUpgrades {
+            package: pkg.clone(),
+            current,
+            direct,
+            patch: check(patch)?,
+            minor: check(minor)?,
+            major: check(major)?,
+        });
+    }
+    Ok(report)
+}
+
+/// Collect the packages, other than the root, whose dependencies on `pkg`
+/// are part of the derivation tree of a failed resolution.
+fn blocking_packages(
+    tree: &DerivationTree<Pkg, SemVer>,
+    pkg: &Pkg,
+    root_pkg: &Pkg,
+    blocked_by: &mut Vec<Pkg>,
+) {
+    match tree {
+        DerivationTree::External(External::FromDependencyOf(p1, _, p2, _)) => {
+            if p2 == pkg && p1 != root_pkg && !blocked_by.contains(p1) {
+                blocked_by.push(p1.clone());
+            }
+        }
+        DerivationTree::External(_) => {}
+        DerivationTree::Derived(derived) => {
+            blocking_packages(&derived.cause1, pkg, root_pkg, blocked_by);
+            blocking_packages(&derived.cause2, pkg, root_pkg, blocked_by);
+        }
+    }
+}
+
+/// A newly published version that could change the solution of a project.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelevantRelease {
+    package: Pkg,
+    version: SemVer,
+    /// Union of the constraints on the package that allow this version.
+    constraint: String,
+}
+
+/// Keep the newly published versions allowed by the constraints reachable from the project.
+pub fn relevant_releases(
+    reach: &Map<Pkg, Range<SemVer>>,
+    new_versions: &[(Pkg, SemVer)],
+) -> Vec<RelevantRelease> {
+    (new_versions.iter())
+        .filter_map(|(pkg, version)| {
+            let range = reach.get(pkg)?;
+            range.contains(version).then(|| RelevantRelease {
+                package: pkg.clone(),
+                version: *version,
+                constraint: range.to_string(),
+            })
+        })
+        .collect()
+}
@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use log::{LevelFilter, Metadata, Record, SetLoggerError};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
 use wasm_bindgen::prelude::*;
 
 pub fn set_panic_hook() {
@@ -18,6 +20,22 @@ pub fn set_panic_hook() {
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     pub fn log(s: &str);
+    #[wasm_bindgen(js_namespace = console)]
+    fn error(s: &str);
+    #[wasm_bindgen(js_namespace = console)]
+    fn warn(s: &str);
+    #[wasm_bindgen(js_namespace = console)]
+    fn info(s: &str);
+    #[wasm_bindgen(js_namespace = console)]
+    fn debug(s: &str);
+    #[wasm_bindgen(js_namespace = console)]
+    fn group(s: &str);
+    #[wasm_bindgen(js_namespace = console, js_name = groupEnd)]
+    fn group_end();
+    #[wasm_bindgen(js_namespace = console)]
+    fn time(label: &str);
+    #[wasm_bindgen(js_namespace = console, js_name = timeEnd)]
+    fn time_end(label: &str);
 }
 
 // Macro console_log! similar to println!
@@ -27,6 +45,7 @@ extern "C" {
 
 // Log implementation
 
+/// Logger writing to the JS console, with the console method matching the log level.
 pub struct WasmLogger;
 
 static LOGGER: WasmLogger = WasmLogger;
@@ -41,21 +60,100 @@ impl WasmLogger {
 }
 
 impl log::Log for WasmLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
     }
 
-    // TODO: instead of silencing logs, we should call a js_sys::Function
-    // passed as argument when initializing the wasm logger.
-    // WasmLogger will not be able to stay static if we do that.
-    // In turn this means we'll need a struct in lib.rs holding a Box<WasmLogger>.
-    fn log(&self, _record: &Record) {
-        // console_log!("{}: {}", record.level(), record.args());
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = format!("{}: {}", record.level(), record.args());
+        match record.level() {
+            Level::Error => error(&message),
+            Level::Warn => warn(&message),
+            Level::Info => info(&message),
+            Level::Debug | Level::Trace => debug(&message),
+        }
     }
 
     fn flush(&self) {}
 }
 
+static GROUP_PHASES: AtomicBool = AtomicBool::new(false);
+static TIME_PHASES: AtomicBool = AtomicBool::new(false);
+
+/// Phase of a solve, grouping its logs with `console.group` until dropped,
+/// and timing it with `console.time` if enabled.
+/// It is also reported as a span.
+pub struct LogPhase {
+    grouped: bool,
+    timed: Option<String>,
+    _span: crate::spans::Span,
+}
+
+impl LogPhase {
+    /// Enable or disable the grouping of logs by phase.
+    pub fn enable(enabled: bool) {
+        GROUP_PHASES.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Enable or disable the timing of phases with `console.time`.
+    pub fn enable_timing(enabled: bool) {
+        TIME_PHASES.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn start(name: &'static str) -> Self {
+        let grouped = GROUP_PHASES.load(Ordering::Relaxed) && log::max_level() > LevelFilter::Off;
+        if grouped {
+            group(name);
+        }
+        let timed = TIME_PHASES.load(Ordering::Relaxed).then(|| {
+            let label = format!("elm-solve-deps {}", name);
+            time(&label);
+            label
+        });
+        Self {
+            grouped,
+            timed,
+            _span: crate::spans::Span::enter(name, &[]),
+        }
+    }
+}
+
+impl Drop for LogPhase {
+    fn drop(&mut self) {
+        if let Some(label) = &self.timed {
+            time_end(label);
+        }
+        if self.grouped {
+            group_end();
+        }
+    }
+}
+
+/// Change the log level until dropped, then restore the previous level.
+pub struct LogLevelGuard(Option<LevelFilter>);
+
+impl LogLevelGuard {
+    pub fn set(level: Option<LevelFilter>) -> Self {
+        let previous = level.map(|level| {
+            let previous = log::max_level();
+            log::set_max_level(level);
+            previous
+        });
+        Self(previous)
+    }
+}
+
+impl Drop for LogLevelGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0 {
+            log::set_max_level(previous);
+        }
+    }
+}
+
 pub fn verbosity_filter(verbosity: u32) -> LevelFilter {
     match verbosity {
         0 => LevelFilter::Error,
@@ -67,8 +165,23 @@ pub fn verbosity_filter(verbosity: u32) -> LevelFilter {
 }
 
 /// Log the error and convert it into a JsValue.
+///
+/// With the `tiny` feature, the error is formatted without its backtrace,
+/// which saves the backtrace formatting code from the wasm.
+/// With the `minimal` feature, only the outermost message of the error is kept.
 pub fn report_error<E: Into<anyhow::Error>>(error: E) -> JsValue {
+    #[cfg(not(feature = "tiny"))]
     let error_msg = format!("{:?}", error.into());
+    #[cfg(all(feature = "tiny", not(feature = "minimal")))]
+    let error_msg = format!("{:#}", error.into());
+    #[cfg(feature = "minimal")]
+    let error_msg = format!("{}", error.into());
     log::error!("{}", &error_msg);
     error_msg.into()
 }
+
+/// Log an error only known by its message, like the boxed errors of the provider,
+/// and convert it into a JsValue.
+pub fn report_error_message<E: std::fmt::Display>(error: E) -> JsValue {
+    report_error(anyhow::anyhow!("{}", error))
+}
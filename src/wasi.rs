@@ -0,0 +1,285 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Line-based JSON protocol to use the solver from non-JS hosts.
+//!
+//! This powers the `elm-solve-deps-wasi` binary, compiled for the `wasm32-wasip1` target,
+//! which can be embedded with wasmtime, or from Go, Python, etc.
+//! Every message is a single line of JSON.
+//!
+//! 1. The host starts by writing the solve request:
+//!    `{"elmJson": "...", "useTest": false, "additionalConstraints": {}, "options": {}}`
+//!    where `options` can be omitted.
+//! 2. The solver then writes requests for the data it needs, one at a time:
+//!    `{"fetchElmJson": {"pkg": "elm/core", "version": "1.0.5"}}`
+//!    or `{"listAvailableVersions": {"pkg": "elm/core"}}`.
+//!    The host must answer each with either `{"ok": ...}`, containing the elm.json string
+//!    or the array of versions (in prefered order), or with `{"error": "..."}`.
+//! 3. Finally, the solver writes `{"solution": {...}}` or `{"error": "..."}`.
+//!
+//! The options are the ones of `solve_deps` in the JS API, read with the same
+//! [SolveOptions], and the solution has the same extra fields.
+//! Only the options taking JS values are not available:
+//! the callbacks like `hasLocally` (so neither `preferLocal` nor `installPlan`),
+//! `signal`, `context` and `thisArg`.
+//! The `output` and `errorTree` options have no effect since messages are always JSON,
+//! and logs are not written, whatever the `logLevel`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anyhow::Context;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+
+use crate::options::SolveOptions;
+use crate::pipeline;
+use crate::provider::JsProvider;
+
+/// Initial request sent by the host.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolveRequest {
+    elm_json: String,
+    #[serde(default)]
+    use_test: bool,
+    #[serde(default)]
+    additional_constraints: HashMap<String, crate::constraints::AdditionalConstraint>,
+    #[serde(default)]
+    options: Option<serde_json::Value>,
+}
+
+/// Data requested to the host during the solve.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum HostRequest<'a> {
+    FetchElmJson { pkg: &'a str, version: SemVer },
+    ListAvailableVersions { pkg: &'a str },
+}
+
+/// Answer of the host to a [HostRequest].
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum HostResponse<T> {
+    Ok(T),
+    Error(String),
+}
+
+/// Final message, sent by the solver.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum SolveResponse {
+    Solution(serde_json::Value),
+    Error(String),
+}
+
+/// Run the protocol until the final solution (or error) is written to `output`.
+///
+/// Only I/O failures of the protocol itself are returned as errors,
+/// solving errors are reported to the host.
+pub fn run<R: BufRead + 'static, W: Write + 'static>(input: R, output: W) -> anyhow::Result<()> {
+    let channel = Rc::new(RefCell::new(Channel { input, output }));
+    let response = match solve(&channel) {
+        Ok(solution) => SolveResponse::Solution(solution),
+        Err(err) => SolveResponse::Error(format!("{:?}", err)),
+    };
+    channel.borrow_mut().send(&response)?;
+    Ok(())
+}
+
+fn solve<R: BufRead + 'static, W: Write + 'static>(
+    channel: &Rc<RefCell<Channel<R, W>>>,
+) -> anyhow::Result<serde_json::Value> {
+    let request: SolveRequest = channel
+        .borrow_mut()
+        .receive()
+        .context("Failed to decode the solve request")?;
+    let options = match request.options {
+        Some(options) => SolveOptions::from_json(options).map_err(anyhow::Error::msg)?,
+        None => SolveOptions::default(),
+    };
+    let (project_elm_json, additional_constraints) =
+        pipeline::read_solve_input(&request.elm_json, request.additional_constraints, &options)?;
+
+    let fetch_channel = Rc::clone(channel);
+    let fetch_elm_json = move |pkg: &str, version: SemVer| {
+        let str_config: String = fetch_channel
+            .borrow_mut()
+            .ask(&HostRequest::FetchElmJson { pkg, version }, || {
+                format!("fetch_elm_json({}, {})", pkg, version)
+            })?;
+        Ok(str_config)
+    };
+
+    let list_channel = Rc::clone(channel);
+    let list_available_versions = move |pkg: &str| {
+        let versions: Vec<String> = list_channel
+            .borrow_mut()
+            .ask(&HostRequest::ListAvailableVersions { pkg }, || {
+                format!("list_available_versions({})", pkg)
+            })?;
+        let versions = versions
+            .iter()
+            .map(|v| SemVer::from_str(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(versions)
+    };
+
+    let provider = JsProvider::with_host(
+        Box::new(fetch_elm_json),
+        Box::new(list_available_versions),
+        &options,
+    );
+    let provider = pipeline::configure_provider(provider, &options, &project_elm_json)?;
+    let solve = (
+        &project_elm_json,
+        request.use_test,
+        &additional_constraints[..],
+    );
+    let solution = pipeline::solve_with_options(&provider, &options, solve)
+        .map_err(|err| pipeline::solve_error(err, &provider, &options, solve))?;
+    pipeline::solution_with_fields(&solution, &provider, &options, solve)
+}
+
+/// Both ends of the line-based protocol.
+struct Channel<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Channel<R, W> {
+    fn send<T: Serialize>(&mut self, message: &T) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.output, message)?;
+        self.output.write_all(b"\n")?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn receive<T: serde::de::DeserializeOwned>(&mut self) -> anyhow::Result<T> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            anyhow::bail!("The host closed the input before answering");
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Send a request to the host and wait for its answer.
+    fn ask<T: serde::de::DeserializeOwned>(
+        &mut self,
+        request: &HostRequest,
+        call: impl Fn() -> String,
+    ) -> anyhow::Result<T> {
+        self.send(request)?;
+        match self.receive()? {
+            HostResponse::Ok(value) => Ok(value),
+            HostResponse::Error(err) => Err(anyhow::anyhow!(
+                "An error occurred in the host answer to `{}`.\n\n{}",
+                call(),
+                err
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn core_elm_json(version: &str) -> String {
+        let elm_json = serde_json::json!({
+            "type": "package",
+            "name": "elm/core",
+            "summary": "",
+            "license": "BSD-3-Clause",
+            "version": version,
+            "exposed-modules": [],
+            "elm-version": "0.19.0 <= v < 0.20.0",
+            "dependencies": {},
+            "test-dependencies": {}
+        });
+        serde_json::json!({ "ok": elm_json.to_string() }).to_string()
+    }
+
+    fn run_lines(lines: &[String]) -> Vec<serde_json::Value> {
+        let input = std::io::Cursor::new(lines.join("\n") + "\n");
+        let output = Rc::new(RefCell::new(Vec::new()));
+        run(input, SharedOutput(Rc::clone(&output))).unwrap();
+        let output = String::from_utf8(output.take()).unwrap();
+        (output.lines())
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// Output kept after `run` consumed its writer.
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request(options: serde_json::Value) -> String {
+        let elm_json = serde_json::json!({
+            "type": "package",
+            "name": "author/project",
+            "summary": "",
+            "license": "BSD-3-Clause",
+            "version": "1.0.0",
+            "exposed-modules": [],
+            "elm-version": "0.19.0 <= v < 0.20.0",
+            "dependencies": { "elm/core": "1.0.0 <= v < 2.0.0" },
+            "test-dependencies": {}
+        });
+        let mut request = serde_json::json!({ "elmJson": elm_json.to_string() });
+        if !options.is_null() {
+            request["options"] = options;
+        }
+        request.to_string()
+    }
+
+    #[test]
+    fn solve_without_options() {
+        let messages = run_lines(&[
+            request(serde_json::Value::Null),
+            r#"{"ok": ["1.0.5", "1.0.4"]}"#.to_string(),
+            core_elm_json("1.0.5"),
+        ]);
+        assert_eq!(
+            messages[0],
+            serde_json::json!({ "listAvailableVersions": { "pkg": "elm/core" } })
+        );
+        assert_eq!(
+            messages[1],
+            serde_json::json!({ "fetchElmJson": { "pkg": "elm/core", "version": "1.0.5" } })
+        );
+        let solution = &messages[2]["solution"];
+        assert_eq!(solution["direct"]["elm/core"], "1.0.5");
+    }
+
+    #[test]
+    fn solve_with_options() {
+        let options = serde_json::json!({ "pins": { "elm/core": "1.0.4" }, "graph": true });
+        let messages = run_lines(&[
+            request(options),
+            r#"{"ok": ["1.0.5", "1.0.4"]}"#.to_string(),
+            core_elm_json("1.0.4"),
+        ]);
+        let solution = &messages[2]["solution"];
+        assert_eq!(solution["direct"]["elm/core"], "1.0.4");
+        assert!(solution.get("graph").is_some());
+    }
+
+    #[test]
+    fn invalid_options_are_reported() {
+        let options = serde_json::json!({ "preferLocal": true });
+        let messages = run_lines(&[request(options)]);
+        let error = messages[0]["error"].as_str().unwrap();
+        assert!(error.contains("`preferLocal` requires the `hasLocally` function"));
+    }
+}